@@ -0,0 +1,407 @@
+#[allow(unused_imports)]
+#[macro_use]
+extern crate destruct;
+
+use destruct::*;
+
+pub mod shrink;
+
+pub use crate::shrink::{minimize, Shrink, ShrinkVariants};
+
+/// A deterministic source of random values.
+///
+/// The same seed always produces the same sequence of values, so a failing property test can be
+/// reproduced by re-running it with the seed it reported.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Gen {
+    state: u64,
+    size: usize,
+    depth: usize,
+}
+
+impl Gen {
+    /// Create a generator with the default size (16) and depth (8) limits.
+    pub fn new(seed: u64) -> Gen {
+        Gen {
+            state: seed,
+            size: 16,
+            depth: 8,
+        }
+    }
+
+    /// Set the upper bound of collection lengths.
+    pub fn with_size(mut self, size: usize) -> Gen {
+        self.size = size;
+        self
+    }
+
+    /// Set how many levels of nested structs and enums may be generated.
+    pub fn with_depth(mut self, depth: usize) -> Gen {
+        self.depth = depth;
+        self
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// The number of nesting levels still available.
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// Generate a value of type `T`.
+    pub fn generate<T: Arbitrary>(&mut self) -> T {
+        T::arbitrary(self)
+    }
+
+    /// Next value of the splitmix64 sequence.
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// A uniformly distributed value in `0..n`. `n` must not be zero.
+    pub fn below(&mut self, n: u64) -> u64 {
+        assert!(n > 0, "Gen::below called with an empty range");
+        // Rejection sampling keeps the distribution uniform.
+        let zone = u64::MAX - u64::MAX % n;
+        loop {
+            let v = self.next_u64();
+            if v < zone {
+                return v % n;
+            }
+        }
+    }
+
+    pub fn gen_bool(&mut self) -> bool {
+        self.next_u64() & 1 == 1
+    }
+
+    /// A collection length in `0..=size`.
+    fn len(&mut self) -> usize {
+        self.below((self.size as u64).saturating_add(1)) as usize
+    }
+
+    /// Run `f` one nesting level deeper.
+    fn nested<T>(&mut self, f: impl FnOnce(&mut Gen) -> T) -> T {
+        let depth = self.depth;
+        self.depth = depth.saturating_sub(1);
+        let result = f(self);
+        self.depth = depth;
+        result
+    }
+}
+
+pub trait Arbitrary: Sized {
+    fn arbitrary(g: &mut Gen) -> Self;
+
+    /// Whether a value can be generated with `depth` nesting levels left.
+    ///
+    /// Enums only pick variants that fit, which is what makes recursive types terminate.
+    fn fits(_depth: usize) -> bool {
+        true
+    }
+}
+
+macro_rules! arbitrary_int {
+    ($($t:ty),*) => {
+        $(
+            impl Arbitrary for $t {
+                fn arbitrary(g: &mut Gen) -> Self {
+                    g.next_u64() as $t
+                }
+            }
+        )*
+    };
+}
+
+arbitrary_int!(u8, u16, u32, u64, usize, i8, i16, i32, i64, isize);
+
+impl Arbitrary for u128 {
+    fn arbitrary(g: &mut Gen) -> Self {
+        (u128::from(g.next_u64()) << 64) | u128::from(g.next_u64())
+    }
+}
+
+impl Arbitrary for i128 {
+    fn arbitrary(g: &mut Gen) -> Self {
+        u128::arbitrary(g) as i128
+    }
+}
+
+impl Arbitrary for bool {
+    fn arbitrary(g: &mut Gen) -> Self {
+        g.gen_bool()
+    }
+}
+
+impl Arbitrary for f32 {
+    fn arbitrary(g: &mut Gen) -> Self {
+        // Finite values only, so generated structs compare equal to themselves.
+        (g.next_u64() as i32) as f32 / (1 << 16) as f32
+    }
+}
+
+impl Arbitrary for f64 {
+    fn arbitrary(g: &mut Gen) -> Self {
+        (g.next_u64() as i64) as f64 / (1u64 << 32) as f64
+    }
+}
+
+impl Arbitrary for char {
+    fn arbitrary(g: &mut Gen) -> Self {
+        loop {
+            if let Some(c) = std::char::from_u32(g.below(0x11_0000) as u32) {
+                return c;
+            }
+        }
+    }
+}
+
+impl Arbitrary for String {
+    fn arbitrary(g: &mut Gen) -> Self {
+        let len = g.len();
+        (0..len).map(|_| char::arbitrary(g)).collect()
+    }
+}
+
+impl Arbitrary for () {
+    fn arbitrary(_: &mut Gen) -> Self {}
+}
+
+impl<T: Arbitrary> Arbitrary for Vec<T> {
+    fn arbitrary(g: &mut Gen) -> Self {
+        if !T::fits(g.depth()) {
+            return Vec::new();
+        }
+        let len = g.len();
+        (0..len).map(|_| T::arbitrary(g)).collect()
+    }
+}
+
+impl<T: Arbitrary> Arbitrary for Option<T> {
+    fn arbitrary(g: &mut Gen) -> Self {
+        if T::fits(g.depth()) && g.gen_bool() {
+            Some(T::arbitrary(g))
+        } else {
+            None
+        }
+    }
+}
+
+impl<T: Arbitrary> Arbitrary for Box<T> {
+    fn arbitrary(g: &mut Gen) -> Self {
+        Box::new(T::arbitrary(g))
+    }
+
+    fn fits(depth: usize) -> bool {
+        T::fits(depth)
+    }
+}
+
+impl<M: DestructMetadata + 'static> Arbitrary for DestructEnd<M> {
+    fn arbitrary(_: &mut Gen) -> Self {
+        DestructEnd::new()
+    }
+}
+
+impl<H: Arbitrary, T: Arbitrary, M: DestructFieldMetadata + 'static> Arbitrary
+    for DestructField<H, T, M>
+{
+    fn arbitrary(g: &mut Gen) -> Self {
+        let head = H::arbitrary(g);
+        DestructField::new(head, T::arbitrary(g))
+    }
+
+    fn fits(depth: usize) -> bool {
+        H::fits(depth) && T::fits(depth)
+    }
+}
+
+impl<F: Arbitrary, M: DestructMetadata + 'static> Arbitrary for DestructBegin<F, M> {
+    fn arbitrary(g: &mut Gen) -> Self {
+        DestructBegin::new(g.nested(F::arbitrary))
+    }
+
+    fn fits(depth: usize) -> bool {
+        depth > 0 && F::fits(depth - 1)
+    }
+}
+
+/// Generation over the variant list of an enum.
+///
+/// Implemented for `DestructEnumVariant` and `DestructEnumEnd`.
+pub trait ArbitraryVariants: Sized {
+    /// Push the `variant_index` of every variant that fits in `depth`.
+    fn fitting_variants(depth: usize, indices: &mut Vec<usize>);
+
+    /// Generate the variant whose `variant_index` is `index`.
+    fn arbitrary_variant(g: &mut Gen, index: usize) -> Self;
+}
+
+impl<M: DestructEnumMetadata + 'static> ArbitraryVariants for DestructEnumEnd<M> {
+    fn fitting_variants(_: usize, _: &mut Vec<usize>) {}
+
+    fn arbitrary_variant(_: &mut Gen, index: usize) -> Self {
+        panic!("enum {} has no variant {}", M::enum_name(), index)
+    }
+}
+
+impl<H: Arbitrary, T: ArbitraryVariants, M: DestructEnumVariantMetadata + 'static> ArbitraryVariants
+    for DestructEnumVariant<H, T, M>
+{
+    fn fitting_variants(depth: usize, indices: &mut Vec<usize>) {
+        if H::fits(depth) {
+            indices.push(M::variant_index());
+        }
+        T::fitting_variants(depth, indices);
+    }
+
+    fn arbitrary_variant(g: &mut Gen, index: usize) -> Self {
+        if index == M::variant_index() {
+            DestructEnumVariant::new_head(H::arbitrary(g))
+        } else {
+            DestructEnumVariant::new_tail(T::arbitrary_variant(g, index))
+        }
+    }
+}
+
+impl<T: ArbitraryVariants, M: DestructEnumMetadata + 'static> Arbitrary
+    for DestructEnumBegin<T, M>
+{
+    /// Pick uniformly among the variants that fit in the remaining depth. When none fits, fall
+    /// back to the variants that fit in the smallest depth, like `Shrink::simplest`, so the
+    /// nesting goes beyond the limit only as far as the type requires.
+    fn arbitrary(g: &mut Gen) -> Self {
+        const MAX_DEPTH: usize = 64;
+        let mut indices = Vec::new();
+        for depth in (g.depth()..=MAX_DEPTH.max(g.depth())).chain(Some(usize::MAX)) {
+            T::fitting_variants(depth, &mut indices);
+            if !indices.is_empty() {
+                break;
+            }
+        }
+        let index = indices[g.below(indices.len() as u64) as usize];
+        DestructEnumBegin::new(T::arbitrary_variant(g, index))
+    }
+
+    fn fits(depth: usize) -> bool {
+        let mut indices = Vec::new();
+        T::fitting_variants(depth, &mut indices);
+        !indices.is_empty()
+    }
+}
+
+/// Use macros to workaround overlapping impls
+#[macro_export]
+macro_rules! arbitrary {
+    ($t:ident) => {
+        impl $crate::Arbitrary for $t {
+            fn arbitrary(g: &mut $crate::Gen) -> Self {
+                <$t as destruct::Destruct>::construct(
+                    <<$t as destruct::Destruct>::DestructType as $crate::Arbitrary>::arbitrary(g),
+                )
+            }
+
+            fn fits(depth: usize) -> bool {
+                <<$t as destruct::Destruct>::DestructType as $crate::Arbitrary>::fits(depth)
+            }
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Destruct, PartialEq, Eq)]
+    #[destruct(arbitrary)]
+    struct Message {
+        id: u32,
+        flags: Vec<bool>,
+        body: Option<String>,
+    }
+
+    #[derive(Debug, Destruct, PartialEq, Eq)]
+    #[destruct(arbitrary)]
+    enum Expr {
+        Add(Box<Expr>, Box<Expr>),
+        Neg(Box<Expr>),
+        Num(u8),
+    }
+
+    fn depth(e: &Expr) -> usize {
+        match e {
+            Expr::Add(a, b) => 1 + depth(a).max(depth(b)),
+            Expr::Neg(a) => 1 + depth(a),
+            Expr::Num(_) => 1,
+        }
+    }
+
+    #[test]
+    fn test_deterministic() {
+        let a: Message = Gen::new(42).generate();
+        let b: Message = Gen::new(42).generate();
+        assert_eq!(a, b);
+        let c: Vec<Message> = Gen::new(7).generate();
+        let d: Vec<Message> = Gen::new(7).generate();
+        assert_eq!(c, d);
+    }
+
+    #[test]
+    fn test_size_limit() {
+        let mut g = Gen::new(1).with_size(3);
+        for _ in 0..100 {
+            let m: Message = g.generate();
+            assert!(m.flags.len() <= 3);
+            assert!(m.body.map_or(0, |b| b.chars().count()) <= 3);
+        }
+    }
+
+    #[test]
+    fn test_recursive_terminates() {
+        let mut g = Gen::new(3).with_depth(4);
+        for _ in 0..200 {
+            let e: Expr = g.generate();
+            assert!(depth(&e) <= 4);
+        }
+        let e: Expr = Gen::new(3).with_depth(1).generate();
+        assert!(matches!(e, Expr::Num(_)));
+    }
+
+    #[derive(Debug, Destruct, PartialEq, Eq)]
+    #[destruct(arbitrary)]
+    struct Wrapper {
+        expr: Expr,
+    }
+
+    #[test]
+    fn test_recursive_beyond_depth() {
+        for seed in 0..50 {
+            let e: Expr = Gen::new(seed).with_depth(0).generate();
+            assert!(matches!(e, Expr::Num(_)), "{:?}", e);
+            let w: Wrapper = Gen::new(seed).with_depth(1).generate();
+            assert!(matches!(w.expr, Expr::Num(_)), "{:?}", w);
+        }
+    }
+
+    #[test]
+    fn test_variants_uniform() {
+        let mut g = Gen::new(5);
+        let mut counts = [0; 3];
+        for _ in 0..300 {
+            let e: Expr = g.generate();
+            let i = match e {
+                Expr::Add(_, _) => 0,
+                Expr::Neg(_) => 1,
+                Expr::Num(_) => 2,
+            };
+            counts[i] += 1;
+        }
+        assert!(counts.iter().all(|&c| c > 50), "{:?}", counts);
+    }
+}
@@ -0,0 +1,526 @@
+use destruct::*;
+
+/// Propose values that are simpler than `self`.
+///
+/// Candidates are ordered from the most aggressive to the least, so `minimize` takes big steps
+/// first.
+pub trait Shrink: Sized {
+    fn shrink(&self) -> Vec<Self>;
+
+    /// The value everything shrinks towards: zero, `false`, `'\0'`, empty, `None`, and for
+    /// enums the variant that nests the least, lowest `variant_index` first. `None` for an enum
+    /// without variants.
+    fn simplest() -> Option<Self>;
+
+    /// Whether `simplest` can build a value with `depth` nesting levels left.
+    ///
+    /// Mirrors `Arbitrary::fits`, so shrinking does not depend on generation.
+    fn fits(_depth: usize) -> bool {
+        true
+    }
+}
+
+/// Shrink `value` for as long as `fails` keeps holding, returning the smallest failing value
+/// found.
+pub fn minimize<T: Shrink>(mut value: T, mut fails: impl FnMut(&T) -> bool) -> T {
+    'shrinking: loop {
+        for candidate in value.shrink() {
+            if fails(&candidate) {
+                value = candidate;
+                continue 'shrinking;
+            }
+        }
+        return value;
+    }
+}
+
+macro_rules! shrink_unsigned {
+    ($($t:ty),*) => {
+        $(
+            impl Shrink for $t {
+                fn shrink(&self) -> Vec<Self> {
+                    let mut result = Vec::new();
+                    for candidate in [0, *self / 2, self.saturating_sub(1)].iter() {
+                        if *candidate < *self && !result.contains(candidate) {
+                            result.push(*candidate);
+                        }
+                    }
+                    result
+                }
+
+                fn simplest() -> Option<Self> {
+                    Some(0)
+                }
+            }
+        )*
+    };
+}
+
+shrink_unsigned!(u8, u16, u32, u64, u128, usize);
+
+macro_rules! shrink_signed {
+    ($($t:ty),*) => {
+        $(
+            impl Shrink for $t {
+                fn shrink(&self) -> Vec<Self> {
+                    let mut result = Vec::new();
+                    let magnitude = |v: $t| v.unsigned_abs();
+                    let candidates = [0, self.checked_neg().unwrap_or(0), *self / 2, *self - self.signum()];
+                    for candidate in candidates.iter() {
+                        let simpler = magnitude(*candidate) < magnitude(*self)
+                            || (magnitude(*candidate) == magnitude(*self) && *candidate > *self);
+                        if simpler && !result.contains(candidate) {
+                            result.push(*candidate);
+                        }
+                    }
+                    result
+                }
+
+                fn simplest() -> Option<Self> {
+                    Some(0)
+                }
+            }
+        )*
+    };
+}
+
+shrink_signed!(i8, i16, i32, i64, i128, isize);
+
+macro_rules! shrink_float {
+    ($($t:ty),*) => {
+        $(
+            impl Shrink for $t {
+                fn shrink(&self) -> Vec<Self> {
+                    let mut result = Vec::new();
+                    for candidate in [0.0, self.trunc(), *self / 2.0].iter() {
+                        if candidate.abs() < self.abs() && !result.contains(candidate) {
+                            result.push(*candidate);
+                        }
+                    }
+                    result
+                }
+
+                fn simplest() -> Option<Self> {
+                    Some(0.0)
+                }
+            }
+        )*
+    };
+}
+
+shrink_float!(f32, f64);
+
+impl Shrink for bool {
+    fn shrink(&self) -> Vec<Self> {
+        if *self {
+            vec![false]
+        } else {
+            Vec::new()
+        }
+    }
+
+    fn simplest() -> Option<Self> {
+        Some(false)
+    }
+}
+
+impl Shrink for char {
+    /// `'\0'`, then `'a'` as a printable step towards it.
+    fn shrink(&self) -> Vec<Self> {
+        ['\0', 'a'].iter().copied().filter(|c| c < self).collect()
+    }
+
+    fn simplest() -> Option<Self> {
+        Some('\0')
+    }
+}
+
+impl Shrink for () {
+    fn shrink(&self) -> Vec<Self> {
+        Vec::new()
+    }
+
+    fn simplest() -> Option<Self> {
+        Some(())
+    }
+}
+
+/// Shorter sequences first (empty, halves, one element removed), then sequences with one element
+/// shrunk.
+fn shrink_seq<T: Clone + Shrink>(items: &[T]) -> Vec<Vec<T>> {
+    let mut result = Vec::new();
+    if items.is_empty() {
+        return result;
+    }
+    result.push(Vec::new());
+    let half = items.len() / 2;
+    if half > 0 {
+        result.push(items[..half].to_vec());
+        result.push(items[half..].to_vec());
+    }
+    if items.len() > 1 {
+        for i in 0..items.len() {
+            let mut shorter = items.to_vec();
+            shorter.remove(i);
+            result.push(shorter);
+        }
+    }
+    for (i, item) in items.iter().enumerate() {
+        for candidate in item.shrink() {
+            let mut simpler = items.to_vec();
+            simpler[i] = candidate;
+            result.push(simpler);
+        }
+    }
+    result
+}
+
+impl<T: Clone + Shrink> Shrink for Vec<T> {
+    fn shrink(&self) -> Vec<Self> {
+        shrink_seq(self)
+    }
+
+    fn simplest() -> Option<Self> {
+        Some(Vec::new())
+    }
+}
+
+impl Shrink for String {
+    fn shrink(&self) -> Vec<Self> {
+        let chars: Vec<char> = self.chars().collect();
+        shrink_seq(&chars)
+            .into_iter()
+            .map(|chars| chars.into_iter().collect())
+            .collect()
+    }
+
+    fn simplest() -> Option<Self> {
+        Some(String::new())
+    }
+}
+
+impl<T: Shrink> Shrink for Option<T> {
+    fn shrink(&self) -> Vec<Self> {
+        match self {
+            None => Vec::new(),
+            Some(value) => {
+                let mut result = vec![None];
+                result.extend(value.shrink().into_iter().map(Some));
+                result
+            }
+        }
+    }
+
+    fn simplest() -> Option<Self> {
+        Some(None)
+    }
+}
+
+impl<T: Shrink> Shrink for Box<T> {
+    fn shrink(&self) -> Vec<Self> {
+        (**self).shrink().into_iter().map(Box::new).collect()
+    }
+
+    fn simplest() -> Option<Self> {
+        T::simplest().map(Box::new)
+    }
+
+    fn fits(depth: usize) -> bool {
+        T::fits(depth)
+    }
+}
+
+impl<M: DestructMetadata + 'static> Shrink for DestructEnd<M> {
+    fn shrink(&self) -> Vec<Self> {
+        Vec::new()
+    }
+
+    fn simplest() -> Option<Self> {
+        Some(DestructEnd::new())
+    }
+}
+
+impl<H: Clone + Shrink, T: Clone + Shrink, M: DestructFieldMetadata + 'static> Shrink
+    for DestructField<H, T, M>
+{
+    /// Shrink the fields one at a time, keeping the others unchanged.
+    fn shrink(&self) -> Vec<Self> {
+        let heads = self
+            .head
+            .shrink()
+            .into_iter()
+            .map(|head| DestructField::new(head, self.tail.clone()));
+        let tails = self
+            .tail
+            .shrink()
+            .into_iter()
+            .map(|tail| DestructField::new(self.head.clone(), tail));
+        heads.chain(tails).collect()
+    }
+
+    fn simplest() -> Option<Self> {
+        Some(DestructField::new(H::simplest()?, T::simplest()?))
+    }
+
+    fn fits(depth: usize) -> bool {
+        H::fits(depth) && T::fits(depth)
+    }
+}
+
+impl<F: Shrink, M: DestructMetadata + 'static> Shrink for DestructBegin<F, M> {
+    fn shrink(&self) -> Vec<Self> {
+        self.fields
+            .shrink()
+            .into_iter()
+            .map(DestructBegin::new)
+            .collect()
+    }
+
+    fn simplest() -> Option<Self> {
+        F::simplest().map(DestructBegin::new)
+    }
+
+    fn fits(depth: usize) -> bool {
+        depth > 0 && F::fits(depth - 1)
+    }
+}
+
+/// Shrinking over the variant list of an enum.
+///
+/// Implemented for `DestructEnumVariant` and `DestructEnumEnd`.
+pub trait ShrinkVariants: Sized {
+    /// The `variant_index` of the variant held by `self`.
+    fn variant_index(&self) -> usize;
+
+    /// Push the simplest value of every variant whose `variant_index` is below `index`, lowest
+    /// index first.
+    fn simpler_variants(index: usize, result: &mut Vec<Self>);
+
+    /// Shrink the fields of the variant held by `self`.
+    fn shrink_variant(&self) -> Vec<Self>;
+
+    /// The simplest value of the first variant that fits in `depth`, see `Shrink::fits`.
+    fn simplest_variant(depth: usize) -> Option<Self>;
+
+    /// Whether any variant fits in `depth`.
+    fn any_fits(depth: usize) -> bool;
+}
+
+impl<M: DestructEnumMetadata + 'static> ShrinkVariants for DestructEnumEnd<M> {
+    fn variant_index(&self) -> usize {
+        panic!("enum {} has no variants", M::enum_name())
+    }
+
+    fn simpler_variants(_: usize, _: &mut Vec<Self>) {}
+
+    fn shrink_variant(&self) -> Vec<Self> {
+        Vec::new()
+    }
+
+    fn simplest_variant(_: usize) -> Option<Self> {
+        None
+    }
+
+    fn any_fits(_: usize) -> bool {
+        false
+    }
+}
+
+impl<H: Shrink, T: ShrinkVariants, M: DestructEnumVariantMetadata + 'static> ShrinkVariants
+    for DestructEnumVariant<H, T, M>
+{
+    fn variant_index(&self) -> usize {
+        match self {
+            DestructEnumVariant::Head(_, _) => M::variant_index(),
+            DestructEnumVariant::Tail(tail, _) => tail.variant_index(),
+        }
+    }
+
+    fn simpler_variants(index: usize, result: &mut Vec<Self>) {
+        if M::variant_index() < index {
+            if let Some(head) = H::simplest() {
+                result.push(DestructEnumVariant::new_head(head));
+            }
+        }
+        let mut tails = Vec::new();
+        T::simpler_variants(index, &mut tails);
+        result.extend(tails.into_iter().map(DestructEnumVariant::new_tail));
+    }
+
+    fn shrink_variant(&self) -> Vec<Self> {
+        match self {
+            DestructEnumVariant::Head(head, _) => head
+                .shrink()
+                .into_iter()
+                .map(DestructEnumVariant::new_head)
+                .collect(),
+            DestructEnumVariant::Tail(tail, _) => tail
+                .shrink_variant()
+                .into_iter()
+                .map(DestructEnumVariant::new_tail)
+                .collect(),
+        }
+    }
+
+    fn simplest_variant(depth: usize) -> Option<Self> {
+        let head = if H::fits(depth) { H::simplest() } else { None };
+        match head {
+            Some(head) => Some(DestructEnumVariant::new_head(head)),
+            None => T::simplest_variant(depth).map(DestructEnumVariant::new_tail),
+        }
+    }
+
+    fn any_fits(depth: usize) -> bool {
+        H::fits(depth) || T::any_fits(depth)
+    }
+}
+
+impl<T: ShrinkVariants, M: DestructEnumMetadata + 'static> Shrink for DestructEnumBegin<T, M> {
+    /// Switch to a simpler variant first, lowest `variant_index` first, then shrink the fields of
+    /// the current one.
+    fn shrink(&self) -> Vec<Self> {
+        let mut variants = Vec::new();
+        T::simpler_variants(self.variants.variant_index(), &mut variants);
+        variants.extend(self.variants.shrink_variant());
+        variants.into_iter().map(DestructEnumBegin::new).collect()
+    }
+
+    /// A variant that fits in the smallest depth holds no value of the enum, so building its
+    /// simplest value terminates.
+    fn simplest() -> Option<Self> {
+        const MAX_DEPTH: usize = 64;
+        (0..=MAX_DEPTH)
+            .find_map(T::simplest_variant)
+            .map(DestructEnumBegin::new)
+    }
+
+    fn fits(depth: usize) -> bool {
+        T::any_fits(depth)
+    }
+}
+
+/// Use macros to workaround overlapping impls
+#[macro_export]
+macro_rules! shrink {
+    ($t:ident) => {
+        impl $crate::Shrink for $t {
+            fn shrink(&self) -> Vec<Self> {
+                $crate::Shrink::shrink(&<$t as destruct::Destruct>::destruct(self.clone()))
+                    .into_iter()
+                    .map(<$t as destruct::Destruct>::construct)
+                    .collect()
+            }
+
+            fn simplest() -> Option<Self> {
+                <<$t as destruct::Destruct>::DestructType as $crate::Shrink>::simplest()
+                    .map(<$t as destruct::Destruct>::construct)
+            }
+
+            fn fits(depth: usize) -> bool {
+                <<$t as destruct::Destruct>::DestructType as $crate::Shrink>::fits(depth)
+            }
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arbitrary;
+
+    #[derive(Debug, Clone, Destruct, PartialEq, Eq)]
+    #[destruct(arbitrary, shrink)]
+    struct Packet {
+        id: u32,
+        payload: Vec<u8>,
+        checked: bool,
+    }
+
+    #[derive(Debug, Clone, Destruct, PartialEq, Eq)]
+    #[destruct(arbitrary, shrink)]
+    enum Expr {
+        Num(u8),
+        Neg(Box<Expr>),
+        Add(Box<Expr>, Box<Expr>),
+    }
+
+    #[derive(Debug, Clone, Destruct, PartialEq, Eq)]
+    #[destruct(shrink)]
+    enum Tree {
+        Leaf(bool),
+        Node(Box<Tree>, Box<Tree>),
+    }
+
+    #[test]
+    fn test_shrink_without_arbitrary() {
+        assert_eq!(Tree::simplest(), Some(Tree::Leaf(false)));
+        let tree = Tree::Node(Box::new(Tree::Leaf(true)), Box::new(Tree::Leaf(false)));
+        assert_eq!(minimize(tree, |_| true), Tree::Leaf(false));
+    }
+
+    #[test]
+    fn test_shrink_numbers() {
+        assert_eq!(10u8.shrink(), vec![0, 5, 9]);
+        assert_eq!(0u8.shrink(), vec![]);
+        assert_eq!((-4i8).shrink(), vec![0, 4, -2, -3]);
+    }
+
+    #[test]
+    fn test_minimize_struct() {
+        let packet = Packet {
+            id: 123_456,
+            payload: vec![9, 200, 7, 31, 64],
+            checked: true,
+        };
+        // Fails whenever the payload holds a byte above 100.
+        let minimal = minimize(packet, |p| p.payload.iter().any(|b| *b > 100));
+        assert_eq!(
+            minimal,
+            Packet {
+                id: 0,
+                payload: vec![101],
+                checked: false,
+            }
+        );
+    }
+
+    #[test]
+    fn test_minimize_enum() {
+        let expr = Expr::Add(
+            Box::new(Expr::Neg(Box::new(Expr::Num(42)))),
+            Box::new(Expr::Add(
+                Box::new(Expr::Num(7)),
+                Box::new(Expr::Neg(Box::new(Expr::Num(3)))),
+            )),
+        );
+        fn has_neg(e: &Expr) -> bool {
+            match e {
+                Expr::Num(_) => false,
+                Expr::Neg(_) => true,
+                Expr::Add(a, b) => has_neg(a) || has_neg(b),
+            }
+        }
+        let minimal = minimize(expr, has_neg);
+        assert_eq!(minimal, Expr::Neg(Box::new(Expr::Num(0))));
+    }
+
+    #[test]
+    fn test_simplest() {
+        assert_eq!(Expr::simplest(), Some(Expr::Num(0)));
+        let packet = Packet {
+            id: 0,
+            payload: Vec::new(),
+            checked: false,
+        };
+        assert_eq!(Packet::simplest(), Some(packet));
+        assert_eq!('x'.shrink(), vec!['\0', 'a']);
+        assert_eq!(minimize('x', |c| *c != 'a'), '\0');
+    }
+
+    #[test]
+    fn test_prefers_lower_variant() {
+        let candidates = Expr::Add(Box::new(Expr::Num(1)), Box::new(Expr::Num(2))).shrink();
+        assert!(matches!(candidates[0], Expr::Num(_)));
+        assert!(matches!(candidates[1], Expr::Neg(_)));
+    }
+}
@@ -3,11 +3,13 @@ extern crate proc_macro;
 extern crate quote;
 
 use proc_macro::TokenStream;
-use syn::export::TokenStream2;
-use syn::parse::{Parse, ParseStream};
+use proc_macro2::TokenStream as TokenStream2;
+use proc_macro2::TokenTree;
+use syn::parse::{Parse, ParseStream, Parser};
 use syn::punctuated::Punctuated;
 use syn::{
-    parenthesized, parse2, Data, DeriveInput, Field, Fields, Ident, LitStr, Result, Token, Variant,
+    parenthesized, parse2, parse_quote, Data, DeriveInput, Field, Fields, GenericParam, Generics,
+    Ident, Lifetime, Lit, LitStr, MetaNameValue, Result, Token, Type, Variant, Visibility,
 };
 use syn::{punctuated, Attribute};
 
@@ -50,10 +52,14 @@ fn convert_fields(fields: &Fields) -> (FieldType, Vec<FieldOrdered>) {
     (field_type, fields)
 }
 
-/// Get a token stream describing the type name of the destructed enum type
+/// Get a token stream describing the type name of the destructed enum type.
+///
+/// `reference` is put in front of every field type: empty for `Destruct`, `&'destruct` for
+/// `DestructRef` and `&'destruct mut` for `DestructMut`.
 fn get_destruct_enum_type(
     name: &Ident,
     variants: &mut punctuated::Iter<Variant>,
+    reference: &TokenStream2,
 ) -> proc_macro2::TokenStream {
     match variants.next() {
         Some(variant) => {
@@ -65,9 +71,9 @@ fn get_destruct_enum_type(
                 name,
                 variant.ident
             );
-            let tail = get_destruct_enum_type(name, variants);
+            let tail = get_destruct_enum_type(name, variants, reference);
             let (_, fields) = convert_fields(&variant.fields);
-            let destruct_type = get_destruct_type(&vname, &mut fields.iter());
+            let destruct_type = get_destruct_type(&vname, &mut fields.iter(), reference);
             quote! {
                 destruct::DestructEnumVariant<destruct::DestructBegin<#destruct_type, #field_struct_metadata_name>, #tail, #metadata_name>
             }
@@ -83,7 +89,7 @@ fn get_destruct_enum_type(
 
 /// Get the pattern matching of From for enums.
 /// It will be placed in:
-/// ```rust, norun
+/// ```ignore
 /// impl From<OritinalEnum> for DestructType {
 ///     fn from(t: OritinalEnum) -> Self {
 ///         destruct::DestructEnumBegin::new(match t {
@@ -136,9 +142,58 @@ fn get_destruct_enum_from(
     }
 }
 
+/// Get the pattern matching of `destruct_ref` and `destruct_mut` for enums.
+/// It will be placed in:
+/// ```ignore
+/// impl<'destruct> DestructRef<'destruct> for OriginalEnum {
+///     fn destruct_ref(&'destruct self) -> Self::DestructRefType {
+///         destruct::DestructEnumBegin::new(match self {
+///             #result
+///         })
+///     }
+/// }
+/// ```
+///
+/// The variants are not converted to the generated variant structs: the borrowed fields are put
+/// into the field list directly.
+fn get_destruct_enum_from_ref(
+    name: &Ident,
+    variants: &mut punctuated::Iter<Variant>,
+) -> proc_macro2::TokenStream {
+    match variants.next() {
+        Some(variant) => {
+            let ident = variant.ident.clone();
+            let (field_type, fields) = convert_fields(&variant.fields);
+            let idents: Vec<Ident> = fields
+                .iter()
+                .map(|f| f.0.ident.clone().unwrap_or(format_ident!("field{}", f.1)))
+                .collect();
+            let mut field_list = quote! { destruct::DestructEnd::new() };
+            for ident in idents.iter().rev() {
+                field_list = quote! { destruct::DestructField::new(#ident, #field_list) };
+            }
+            let pattern = match field_type {
+                FieldType::Named => quote! { #name::#ident { #(#idents,)* } },
+                FieldType::Unnamed => quote! { #name::#ident ( #(#idents,)* ) },
+                FieldType::Unit => quote! { #name::#ident },
+            };
+            let tail = get_destruct_enum_from_ref(name, variants);
+            quote! {
+                #pattern => destruct::DestructEnumVariant::new_head(destruct::DestructBegin::new(#field_list)),
+                other_case => destruct::DestructEnumVariant::new_tail(match other_case { #tail })
+            }
+        }
+        None => {
+            quote! {
+                _ => destruct::DestructEnumEnd::new()
+            }
+        }
+    }
+}
+
 /// Get the pattern matching of Into for enums. It will be placed in:
 ///
-/// ```rust, norun
+/// ```ignore
 /// impl Into<OritinalEnum> for DestructType {
 ///     fn into(self) -> OriginalEnum {
 ///         match self.variants {
@@ -183,10 +238,11 @@ fn get_destruct_enum_into(
     }
 }
 
-/// Get the type name of destructed type.
+/// Get the type name of destructed type. `reference` is put in front of every field type.
 fn get_destruct_type(
     name: &Ident,
     fields: &mut std::slice::Iter<FieldOrdered>,
+    reference: &TokenStream2,
 ) -> proc_macro2::TokenStream {
     match fields.next() {
         Some(head_field) => {
@@ -197,9 +253,9 @@ fn get_destruct_type(
                 .unwrap_or(format_ident!("unnamed_{}", head_field.1));
             let metadata_name = format_ident!("_destruct_{}_field_{}_meta", name, head_name);
             let head = head_field.0.ty.clone();
-            let tail = get_destruct_type(name, fields);
+            let tail = get_destruct_type(name, fields, reference);
             quote! {
-                destruct::DestructField<#head, #tail, #metadata_name>
+                destruct::DestructField<#reference #head, #tail, #metadata_name>
             }
         }
         None => {
@@ -213,27 +269,33 @@ fn get_destruct_type(
 
 /// Get the field assignments of the from function.
 /// It will be placed in:
-/// ```rust, no_run
+/// ```ignore
 /// impl From<OriginalStruct> for DestructType {
 ///     fn from(value: OriginalStruct) -> Self {
 ///         #result
 ///     }
 /// }
 /// ```
-fn get_destruct_from(fields: &mut std::slice::Iter<FieldOrdered>) -> proc_macro2::TokenStream {
+///
+/// `access` is put in front of every field access, `&` or `&mut` borrows the fields instead of
+/// moving them.
+fn get_destruct_from(
+    fields: &mut std::slice::Iter<FieldOrdered>,
+    access: &TokenStream2,
+) -> proc_macro2::TokenStream {
     match fields.next() {
         Some(head_field) => {
-            let tail = get_destruct_from(fields);
+            let tail = get_destruct_from(fields, access);
             match head_field.0.ident.clone() {
                 Some(head) => {
                     quote! {
-                        destruct::DestructField::new(t.#head, #tail)
+                        destruct::DestructField::new(#access t.#head, #tail)
                     }
                 }
                 None => {
                     let i = proc_macro2::Literal::usize_unsuffixed(head_field.1);
                     quote! {
-                        destruct::DestructField::new(t.#i, #tail)
+                        destruct::DestructField::new(#access t.#i, #tail)
                     }
                 }
             }
@@ -248,7 +310,7 @@ fn get_destruct_from(fields: &mut std::slice::Iter<FieldOrdered>) -> proc_macro2
 
 /// Get the field assignments of the into function.
 /// It will be placed in:
-/// ```rust, no_run
+/// ```ignore
 /// impl Into<OriginalStruct> for DestructType {
 ///     fn into(self) -> OriginalStruct {
 ///         OriginalStruct #result;
@@ -309,6 +371,18 @@ fn get_destruct_field_meta(
         let lit_name = LitStr::new(s.as_str(), name.span());
         let s = format!("{}", field_name);
         let field_lit_name = LitStr::new(s.as_str(), field_name.span());
+        let attrs = FieldAttrs::from_field(&field.0);
+        let sensitive = attrs.sensitive;
+        let meta_fn = if attrs.meta.is_empty() {
+            None
+        } else {
+            let (keys, values): (Vec<_>, Vec<_>) = attrs.meta.into_iter().unzip();
+            Some(quote! {
+                fn meta() -> &'static [(&'static str, &'static str)] {
+                    &[#((#keys, #values)),*]
+                }
+            })
+        };
         tokens.extend(quote! {
             #[allow(non_camel_case_types)]
             #[derive(Debug, PartialEq, Eq)]
@@ -329,15 +403,117 @@ fn get_destruct_field_meta(
                 fn field_index() -> usize {
                     #field_index
                 }
+                fn sensitive() -> bool {
+                    #sensitive
+                }
+                #meta_fn
             }
         });
     }
     tokens
 }
 
+/// Whether `lifetime` appears in `tokens`.
+fn uses_lifetime(tokens: TokenStream2, lifetime: &Lifetime) -> bool {
+    let mut tokens = tokens.into_iter().peekable();
+    while let Some(token) = tokens.next() {
+        match token {
+            TokenTree::Group(group) if uses_lifetime(group.stream(), lifetime) => return true,
+            TokenTree::Punct(punct) if punct.as_char() == '\'' => {
+                if let Some(TokenTree::Ident(ident)) = tokens.peek() {
+                    if *ident == lifetime.ident {
+                        return true;
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    false
+}
+
+/// The lifetimes of `generics` used by `fields`, for the generated variant struct of an enum.
+/// Unused lifetime parameters are an error, so a variant only gets the ones it needs.
+fn variant_generics(generics: &Generics, fields: &Fields) -> Generics {
+    let mut result = Generics::default();
+    for lifetime in generics.lifetimes() {
+        if uses_lifetime(quote! { #fields }, &lifetime.lifetime) {
+            let lifetime = lifetime.lifetime.clone();
+            result.params.push(parse_quote! { #lifetime });
+        }
+    }
+    result
+}
+
+/// `generics` with the `'destruct` lifetime of `DestructRef` and `DestructMut` added, outlived by
+/// every other lifetime.
+fn destruct_ref_generics(generics: &Generics) -> Generics {
+    let mut result = generics.clone();
+    result.params.insert(0, parse_quote! { 'destruct });
+    let where_clause = result.make_where_clause();
+    for lifetime in generics.lifetimes() {
+        let lifetime = &lifetime.lifetime;
+        where_clause
+            .predicates
+            .push(parse_quote! { #lifetime: 'destruct });
+    }
+    result
+}
+
+fn is_destruct_attr(attr: &Attribute) -> bool {
+    attr.path.is_ident("destruct")
+}
+
+/// Options of a field, given by `#[destruct(...)]` on the field.
+#[derive(Default)]
+struct FieldAttrs {
+    /// `sensitive`: the value must not show up in printed or encoded output.
+    sensitive: bool,
+    /// `default`: the builder fills in `Default::default()` when the field is not set.
+    default: bool,
+    /// `meta(key = "value", ...)`: pairs passed on to `DestructFieldMetadata::meta`.
+    meta: Vec<(String, String)>,
+}
+
+impl FieldAttrs {
+    fn from_field(field: &Field) -> FieldAttrs {
+        let mut result = FieldAttrs::default();
+        for attr in field.attrs.iter().filter(|a| is_destruct_attr(a)) {
+            let args: DestructArgs = syn::parse2(attr.tokens.clone()).unwrap();
+            for option in args.fields.iter() {
+                let ident = &option.name;
+                if ident == "sensitive" {
+                    result.sensitive = true;
+                } else if ident == "default" {
+                    result.default = true;
+                } else if ident == "meta" {
+                    let tokens = option
+                        .args
+                        .clone()
+                        .expect("expected `meta(key = \"value\")`");
+                    let pairs = Punctuated::<MetaNameValue, Token![,]>::parse_terminated
+                        .parse2(tokens)
+                        .unwrap();
+                    for pair in pairs {
+                        let key = pair.path.get_ident().expect("expected a meta key");
+                        match pair.lit {
+                            Lit::Str(value) => result.meta.push((key.to_string(), value.value())),
+                            _ => panic!("the value of meta key `{}` must be a string", key),
+                        }
+                    }
+                } else {
+                    panic!("unknown destruct field attribute `{}`", ident);
+                }
+            }
+        }
+        result
+    }
+}
+
 struct DestructArgs {
+    #[allow(dead_code)]
     brace_token: syn::token::Paren,
-    fields: Punctuated<Ident, Token![,]>,
+    fields: Punctuated<DestructOption, Token![,]>,
 }
 
 impl Parse for DestructArgs {
@@ -345,55 +521,153 @@ impl Parse for DestructArgs {
         let content;
         Ok(DestructArgs {
             brace_token: parenthesized!(content in input),
-            fields: content.parse_terminated(Ident::parse)?,
+            fields: content.parse_terminated(DestructOption::parse)?,
         })
     }
 }
 
+/// An item of `#[destruct(...)]`: a name, optionally followed by arguments in parentheses, as in
+/// `parsable(big_endian)`.
+struct DestructOption {
+    name: Ident,
+    args: Option<TokenStream2>,
+}
+
+impl Parse for DestructOption {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let name = input.parse()?;
+        let args = if input.peek(syn::token::Paren) {
+            let content;
+            parenthesized!(content in input);
+            Some(content.parse()?)
+        } else {
+            None
+        };
+        Ok(DestructOption { name, args })
+    }
+}
+
 #[proc_macro_derive(Destruct, attributes(destruct))]
 pub fn derive_destruct(input: TokenStream) -> TokenStream {
     let input = proc_macro2::TokenStream::from(input);
     let input: DeriveInput = parse2(input).unwrap();
     let name = input.ident;
-    let attrs: Vec<&Attribute> = input
-        .attrs
+    let vis = input.vis;
+    let generics = input.generics;
+    if generics
+        .params
         .iter()
-        .filter(|a| a.path.get_ident().unwrap() == "destruct")
-        .collect();
+        .any(|param| !matches!(param, GenericParam::Lifetime(_)))
+    {
+        panic!("derive Destruct supports only lifetime parameters");
+    }
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let ref_generics = destruct_ref_generics(&generics);
+    let (ref_impl_generics, _, ref_where_clause) = ref_generics.split_for_impl();
+    let attrs: Vec<&Attribute> = input.attrs.iter().filter(|a| is_destruct_attr(a)).collect();
 
     if attrs.len() > 1 {
         panic!("only one destruct attribute is allowed");
     }
+    let options: Vec<DestructOption> = match attrs.first() {
+        Some(attr) => {
+            let args: DestructArgs = syn::parse2(attr.tokens.clone()).unwrap();
+            args.fields.into_iter().collect()
+        }
+        None => Vec::new(),
+    };
+    // `builder` is generated here, every other option names a macro to invoke.
+    let builder = options.iter().any(|option| option.name == "builder");
 
     let mut result = match input.data {
         Data::Struct(s) => {
+            let choice = options
+                .iter()
+                .filter(|option| option.name == "parsable")
+                .filter_map(|option| option.args.clone())
+                .flatten()
+                .find_map(|token| match token {
+                    TokenTree::Ident(ident)
+                        if ident == "ordered" || ident == "longest" || ident == "strict" =>
+                    {
+                        Some(ident)
+                    }
+                    _ => None,
+                });
+            if let Some(choice) = choice {
+                panic!(
+                    "`{}` picks among the variants of an enum, not for a struct",
+                    choice
+                );
+            }
             let (field_type, fields) = convert_fields(&s.fields);
             let s = format!("{}", name);
             let lit_name = LitStr::new(s.as_str(), name.span());
-            derive_struct(name.clone(), lit_name, field_type, fields)
+            let mut tt = TokenStream2::new();
+            if builder {
+                if field_type != FieldType::Named {
+                    panic!("destruct builder supports only structs with named fields");
+                }
+                if !generics.params.is_empty() {
+                    panic!("destruct builder does not support lifetime parameters");
+                }
+                tt.extend(derive_builder(&name, &vis, &fields));
+            }
+            tt.extend(derive_struct(
+                name.clone(),
+                lit_name,
+                field_type,
+                fields,
+                &generics,
+            ));
+            tt
         }
         Data::Enum(e) => {
+            if builder {
+                panic!("destruct builder supports only structs");
+            }
             let mut tt = TokenStream2::new();
             let s = format!("{}", name);
             let lit_name = LitStr::new(s.as_str(), name.span());
+            // The last explicit discriminant and how many variants follow it.
+            let mut discriminant = None;
             for (variant_index, variant) in e.variants.iter().enumerate() {
+                discriminant = match (&variant.discriminant, discriminant) {
+                    (Some((_, expr)), _) => Some((expr, 0usize)),
+                    (None, Some((expr, offset))) => Some((expr, offset + 1)),
+                    (None, None) => None,
+                };
+                let discriminant_fn = discriminant.map(|(expr, offset)| {
+                    let offset = offset as i128;
+                    quote! {
+                        fn discriminant() -> i128 {
+                            (#expr) as i128 + #offset
+                        }
+                    }
+                });
                 let vname = format_ident!("_destruct_enum_{}_variant_{}", name, variant.ident);
                 let meta_name =
                     format_ident!("_destruct_enum_{}_variant_{}_meta", name, variant.ident);
-                let vfields = variant.fields.clone();
+                // Field attributes of destruct are meant for this derive, the generated variant
+                // struct does not derive Destruct itself.
+                let mut vfields = variant.fields.clone();
+                for field in vfields.iter_mut() {
+                    field.attrs.retain(|a| !is_destruct_attr(a));
+                }
                 let (field_type, fields) = convert_fields(&variant.fields);
                 let struct_is_named = field_type == FieldType::Named;
+                let vgenerics = variant_generics(&generics, &variant.fields);
                 if struct_is_named {
                     tt.extend(quote! {
                         #[allow(non_camel_case_types)]
                         #[derive(Debug, PartialEq, Eq)]
-                        pub struct #vname #vfields
+                        #vis struct #vname #vgenerics #vfields
                     });
                 } else {
                     tt.extend(quote! {
                         #[allow(non_camel_case_types)]
                         #[derive(Debug, PartialEq, Eq)]
-                        pub struct #vname #vfields;
+                        #vis struct #vname #vgenerics #vfields;
                     });
                 }
                 let s = format!("{}", name);
@@ -423,21 +697,28 @@ pub fn derive_destruct(input: TokenStream) -> TokenStream {
                         fn variant_index() -> usize {
                             #variant_index
                         }
+                        #discriminant_fn
                     }
                 });
-                let s = format!("{}::{}", name, vname);
-                let lit_name = LitStr::new(s.as_str(), name.span());
-                tt.extend(derive_struct(vname, lit_name, field_type, fields));
+                tt.extend(derive_struct(
+                    vname, lit_vname, field_type, fields, &vgenerics,
+                ));
             }
             let destruct_enum_meta_name = format_ident!("_destruct_enum_{}_meta", name);
-            let destruct_enum_type = get_destruct_enum_type(&name, &mut e.variants.iter());
+            let destruct_enum_type =
+                get_destruct_enum_type(&name, &mut e.variants.iter(), &quote! {});
+            let destruct_enum_ref_type =
+                get_destruct_enum_type(&name, &mut e.variants.iter(), &quote! { &'destruct });
+            let destruct_enum_mut_type =
+                get_destruct_enum_type(&name, &mut e.variants.iter(), &quote! { &'destruct mut });
             let destruct_enum_from = get_destruct_enum_from(&name, &mut e.variants.iter());
+            let destruct_enum_from_ref = get_destruct_enum_from_ref(&name, &mut e.variants.iter());
             let destruct_enum_into = get_destruct_enum_into(&name, &mut e.variants.iter());
             quote! {
                 #tt
 
-                impl From<#name> for destruct::DestructEnumBegin<#destruct_enum_type, #destruct_enum_meta_name> {
-                    fn from(t: #name) -> Self {
+                impl #impl_generics From<#name #ty_generics> for destruct::DestructEnumBegin<#destruct_enum_type, #destruct_enum_meta_name> #where_clause {
+                    fn from(t: #name #ty_generics) -> Self {
                         destruct::DestructEnumBegin::new(match t {#destruct_enum_from})
                     }
                 }
@@ -452,15 +733,15 @@ pub fn derive_destruct(input: TokenStream) -> TokenStream {
                     }
                 }
 
-                impl Into<#name> for destruct::DestructEnumBegin<#destruct_enum_type, #destruct_enum_meta_name> {
-                    fn into(self) -> #name {
+                impl #impl_generics Into<#name #ty_generics> for destruct::DestructEnumBegin<#destruct_enum_type, #destruct_enum_meta_name> #where_clause {
+                    fn into(self) -> #name #ty_generics {
                         match self.variants {
                             #destruct_enum_into
                         }
                     }
                 }
 
-                impl destruct::Destruct for #name {
+                impl #impl_generics destruct::Destruct for #name #ty_generics #where_clause {
                     type DestructType = destruct::DestructEnumBegin<#destruct_enum_type, #destruct_enum_meta_name>;
 
                     fn destruct(self) -> Self::DestructType {
@@ -471,18 +752,55 @@ pub fn derive_destruct(input: TokenStream) -> TokenStream {
                         d.into()
                     }
                 }
+
+                impl #ref_impl_generics destruct::DestructRef<'destruct> for #name #ty_generics #ref_where_clause {
+                    type DestructRefType = destruct::DestructEnumBegin<#destruct_enum_ref_type, #destruct_enum_meta_name>;
+
+                    fn destruct_ref(&'destruct self) -> Self::DestructRefType {
+                        destruct::DestructEnumBegin::new(match self {#destruct_enum_from_ref})
+                    }
+                }
+
+                impl #ref_impl_generics destruct::DestructMut<'destruct> for #name #ty_generics #ref_where_clause {
+                    type DestructMutType = destruct::DestructEnumBegin<#destruct_enum_mut_type, #destruct_enum_meta_name>;
+
+                    fn destruct_mut(&'destruct mut self) -> Self::DestructMutType {
+                        destruct::DestructEnumBegin::new(match self {#destruct_enum_from_ref})
+                    }
+                }
             }
         }
         _ => panic!("derive Destruct supports only structs and enums"),
     };
-    if !attrs.is_empty() {
-        let attr = attrs[0];
-        let args: DestructArgs = syn::parse2(attr.tokens.clone()).unwrap();
-
-        for ident in args.fields.iter() {
-            result.extend(quote! {
+    // A type with lifetimes is passed on as `Name<'a>`.
+    let lifetimes: Vec<&Lifetime> = generics.lifetimes().map(|l| &l.lifetime).collect();
+    let name = if lifetimes.is_empty() {
+        quote! { #name }
+    } else {
+        quote! { #name<#(#lifetimes),*> }
+    };
+    let parsable = options.iter().find(|option| option.name == "parsable");
+    for option in options.iter().filter(|option| option.name != "builder") {
+        let ident = &option.name;
+        // A type that is parsed too emits with the options it is parsed with.
+        let inherits = parsable.is_some() && option.name == "emit";
+        let args = match parsable {
+            Some(_) if inherits && option.args.is_some() => {
+                panic!(
+                    "with `parsable`, `emit` takes its options, give them once in `parsable(...)`"
+                )
+            }
+            Some(parsable) if inherits => &parsable.args,
+            _ => &option.args,
+        };
+        // `#[destruct(parsable(big_endian))]` invokes `parsable!(Name, big_endian)`.
+        match args {
+            Some(args) => result.extend(quote! {
+                #ident!(#name, #args);
+            }),
+            None => result.extend(quote! {
                 #ident!(#name);
-            });
+            }),
         }
     }
 
@@ -496,15 +814,25 @@ pub fn derive_destruct(input: TokenStream) -> TokenStream {
 /// - lit_name: The name of the struct. In the case of enum variants, it's "Enum::Variant".
 /// - struct_is_named: Whether the struct definition is named.
 /// - fields: The field of the struct or enum variant.
+/// - generics: The lifetime parameters of the struct.
 fn derive_struct(
     name: Ident,
     lit_name: LitStr,
     field_type: FieldType,
     fields: Vec<FieldOrdered>,
+    generics: &Generics,
 ) -> TokenStream2 {
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let ref_generics = destruct_ref_generics(generics);
+    let (ref_impl_generics, _, ref_where_clause) = ref_generics.split_for_impl();
     let struct_is_named = field_type == FieldType::Named;
-    let destruct_type = get_destruct_type(&name, &mut fields.iter());
-    let destruct_from = get_destruct_from(&mut fields.iter());
+    let destruct_type = get_destruct_type(&name, &mut fields.iter(), &quote! {});
+    let destruct_ref_type = get_destruct_type(&name, &mut fields.iter(), &quote! { &'destruct });
+    let destruct_mut_type =
+        get_destruct_type(&name, &mut fields.iter(), &quote! { &'destruct mut });
+    let destruct_from = get_destruct_from(&mut fields.iter(), &quote! {});
+    let destruct_from_ref = get_destruct_from(&mut fields.iter(), &quote! { & });
+    let destruct_from_mut = get_destruct_from(&mut fields.iter(), &quote! { &mut });
     let self_name = format_ident!("self");
     let destruct_into = if field_type == FieldType::Unit {
         TokenStream2::new()
@@ -517,8 +845,8 @@ fn derive_struct(
 
     // Return the generated impl
     let output = quote! {
-        impl From<#name> for destruct::DestructBegin<#destruct_type, #destruct_meta_name> {
-            fn from(t: #name) -> Self {
+        impl #impl_generics From<#name #ty_generics> for destruct::DestructBegin<#destruct_type, #destruct_meta_name> #where_clause {
+            fn from(t: #name #ty_generics) -> Self {
                 destruct::DestructBegin::new(#destruct_from)
             }
         }
@@ -538,13 +866,13 @@ fn derive_struct(
 
         #destruct_field_meta
 
-        impl Into<#name> for destruct::DestructBegin<#destruct_type, #destruct_meta_name> {
-            fn into(self) -> #name {
+        impl #impl_generics Into<#name #ty_generics> for destruct::DestructBegin<#destruct_type, #destruct_meta_name> #where_clause {
+            fn into(self) -> #name #ty_generics {
                 #name #destruct_into
             }
         }
 
-        impl destruct::Destruct for #name {
+        impl #impl_generics destruct::Destruct for #name #ty_generics #where_clause {
             type DestructType = destruct::DestructBegin<#destruct_type, #destruct_meta_name>;
 
             fn destruct(self) -> Self::DestructType {
@@ -555,6 +883,116 @@ fn derive_struct(
                 d.into()
             }
         }
+
+        impl #ref_impl_generics destruct::DestructRef<'destruct> for #name #ty_generics #ref_where_clause {
+            type DestructRefType = destruct::DestructBegin<#destruct_ref_type, #destruct_meta_name>;
+
+            #[allow(unused_variables)]
+            fn destruct_ref(&'destruct self) -> Self::DestructRefType {
+                let t = self;
+                destruct::DestructBegin::new(#destruct_from_ref)
+            }
+        }
+
+        impl #ref_impl_generics destruct::DestructMut<'destruct> for #name #ty_generics #ref_where_clause {
+            type DestructMutType = destruct::DestructBegin<#destruct_mut_type, #destruct_meta_name>;
+
+            #[allow(unused_variables)]
+            fn destruct_mut(&'destruct mut self) -> Self::DestructMutType {
+                let t = self;
+                destruct::DestructBegin::new(#destruct_from_mut)
+            }
+        }
     };
     output
 }
+
+/// Generate a type-state builder for a struct with named fields.
+///
+/// The builder has one type parameter per field, `Missing` or `Defaulted` until the setter is
+/// called and `Set<T>` afterwards, so `build` only exists once every required field is set:
+/// ```ignore
+/// pub struct ConfigBuilder<S0, S1> { name: S0, port: S1 }
+///
+/// impl<S0, S1> ConfigBuilder<S0, S1> {
+///     pub fn name(self, value: String) -> ConfigBuilder<destruct::builder::Set<String>, S1>;
+///     ...
+/// }
+///
+/// impl<S0: destruct::builder::Slot<String>, S1: destruct::builder::Slot<u16>> ConfigBuilder<S0, S1> {
+///     pub fn build(self) -> Config;
+/// }
+/// ```
+fn derive_builder(name: &Ident, vis: &Visibility, fields: &[FieldOrdered]) -> TokenStream2 {
+    let builder_name = format_ident!("{}Builder", name);
+    let idents: Vec<Ident> = fields.iter().map(|f| f.0.ident.clone().unwrap()).collect();
+    let types: Vec<Type> = fields.iter().map(|f| f.0.ty.clone()).collect();
+    let params: Vec<Ident> = fields.iter().map(|f| format_ident!("S{}", f.1)).collect();
+    let initial: Vec<TokenStream2> = fields
+        .iter()
+        .map(|f| {
+            if FieldAttrs::from_field(&f.0).default {
+                quote! { destruct::builder::Defaulted }
+            } else {
+                quote! { destruct::builder::Missing }
+            }
+        })
+        .collect();
+
+    let mut setters = TokenStream2::new();
+    for (i, (ident, ty)) in idents.iter().zip(types.iter()).enumerate() {
+        let state = params.iter().enumerate().map(|(j, param)| {
+            if i == j {
+                quote! { destruct::builder::Set<#ty> }
+            } else {
+                quote! { #param }
+            }
+        });
+        let values = idents.iter().enumerate().map(|(j, other)| {
+            if i == j {
+                quote! { #other: destruct::builder::Set(value) }
+            } else {
+                quote! { #other: self.#other }
+            }
+        });
+        setters.extend(quote! {
+            #vis fn #ident(self, value: #ty) -> #builder_name<#(#state),*> {
+                #builder_name { #(#values,)* }
+            }
+        });
+    }
+
+    let mut destruct_fields = quote! { destruct::DestructEnd::new() };
+    for ident in idents.iter().rev() {
+        destruct_fields = quote! {
+            destruct::DestructField::new(destruct::builder::Slot::take(self.#ident), #destruct_fields)
+        };
+    }
+    let builder_doc = format!("Builder of `{}`, created by `{}::builder()`.", name, name);
+
+    quote! {
+        #[doc = #builder_doc]
+        #[must_use]
+        #vis struct #builder_name<#(#params),*> {
+            #(#idents: #params,)*
+        }
+
+        impl #name {
+            #vis fn builder() -> #builder_name<#(#initial),*> {
+                #builder_name {
+                    #(#idents: #initial,)*
+                }
+            }
+        }
+
+        impl<#(#params),*> #builder_name<#(#params),*> {
+            #setters
+        }
+
+        impl<#(#params: destruct::builder::Slot<#types>),*> #builder_name<#(#params),*> {
+            #vis fn build(self) -> #name {
+                <#name as destruct::Destruct>::construct(destruct::DestructBegin::new(#destruct_fields))
+            }
+        }
+    }
+}
@@ -0,0 +1,117 @@
+//! States of the fields of a builder generated by `#[destruct(builder)]`.
+//!
+//! ```
+//! # extern crate destruct_lib as destruct;
+//! # use destruct::Destruct;
+//! # use destruct_derive::Destruct;
+//! #[derive(Destruct)]
+//! #[destruct(builder)]
+//! struct Config {
+//!     name: String,
+//!     port: u16,
+//!     #[destruct(default)]
+//!     verbose: bool,
+//! }
+//!
+//! let config = Config::builder().port(80).name("main".to_string()).build();
+//! assert!(!config.verbose);
+//! ```
+//!
+//! Each field of `ConfigBuilder` starts as `Missing` (or `Defaulted` with `#[destruct(default)]`)
+//! and becomes `Set<T>` once its setter is called. `build` requires every field to be a `Slot`,
+//! so leaving out a required field is a compile error:
+//!
+//! ```compile_fail,E0277
+//! # extern crate destruct_lib as destruct;
+//! # use destruct::Destruct;
+//! # use destruct_derive::Destruct;
+//! # #[derive(Destruct)]
+//! # #[destruct(builder)]
+//! # struct Config {
+//! #     name: String,
+//! #     port: u16,
+//! # }
+//! let config = Config::builder().name("main".to_string()).build();
+//! ```
+//!
+//! Calling a setter again replaces the value, as assigning a field would.
+
+/// A required field that has not been set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Missing;
+
+/// A field that falls back to `Default::default()` when not set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Defaulted;
+
+/// A field that has been set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Set<T>(pub T);
+
+/// A field state that can produce the field value.
+#[diagnostic::on_unimplemented(
+    message = "required field of type `{T}` has not been set",
+    label = "call the setter of this field before `build`"
+)]
+pub trait Slot<T> {
+    fn take(self) -> T;
+}
+
+impl<T> Slot<T> for Set<T> {
+    fn take(self) -> T {
+        self.0
+    }
+}
+
+impl<T: Default> Slot<T> for Defaulted {
+    fn take(self) -> T {
+        T::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate as destruct;
+
+    #[derive(Debug, Destruct, PartialEq)]
+    #[destruct(builder)]
+    struct Config {
+        name: String,
+        port: u16,
+        #[destruct(default)]
+        verbose: bool,
+        #[destruct(default)]
+        tags: Vec<String>,
+    }
+
+    #[test]
+    fn test_build() {
+        let config = Config::builder()
+            .port(80)
+            .name("main".to_string())
+            .build();
+        assert_eq!(
+            config,
+            Config {
+                name: "main".to_string(),
+                port: 80,
+                verbose: false,
+                tags: vec![],
+            }
+        );
+    }
+
+    #[test]
+    fn test_build_all() {
+        let config = Config::builder()
+            .name("main".to_string())
+            .port(80)
+            .verbose(true)
+            .tags(vec!["a".to_string()])
+            .port(81)
+            .build();
+        assert_eq!(config.port, 81);
+        assert!(config.verbose);
+        assert_eq!(config.tags, vec!["a".to_string()]);
+    }
+}
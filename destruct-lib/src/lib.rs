@@ -10,11 +10,15 @@ extern crate derive_new;
 
 use std::marker::PhantomData;
 
+pub mod builder;
+pub mod path;
+pub mod print;
+
 pub trait Destruct: Sized {
     /// The destructed object type
     ///
     /// If your struct is:
-    /// ```rust,no-run
+    /// ```ignore
     /// #[derive(Destruct)]
     /// struct YourStruct {
     ///     field: YourField,
@@ -40,6 +44,27 @@ pub trait Destruct: Sized {
     fn construct(d: Self::DestructType) -> Self;
 }
 
+/// Destruct a borrowed value. Derived together with `Destruct`.
+pub trait DestructRef<'a> {
+    /// The same list as `Destruct::DestructType`, with every field type `F` replaced by `&'a F`.
+    ///
+    /// For enums, the head of each variant is `DestructBegin` of the borrowed variant fields
+    /// instead of the destructed variant struct.
+    type DestructRefType;
+
+    /// Destruct a reference to self into references to the fields
+    fn destruct_ref(&'a self) -> Self::DestructRefType;
+}
+
+/// Destruct a mutably borrowed value. Derived together with `Destruct`.
+pub trait DestructMut<'a> {
+    /// Like `DestructRef::DestructRefType`, with every field type `F` replaced by `&'a mut F`.
+    type DestructMutType;
+
+    /// Destruct a mutable reference to self into mutable references to the fields
+    fn destruct_mut(&'a mut self) -> Self::DestructMutType;
+}
+
 pub trait DestructMetadata {
     fn struct_name() -> &'static str;
     fn named_fields() -> bool;
@@ -55,6 +80,19 @@ pub struct DestructBegin<T, M: DestructMetadata + 'static> {
 pub trait DestructFieldMetadata: DestructMetadata + 'static {
     fn field_name() -> &'static str;
     fn field_index() -> usize;
+
+    /// Whether the field is marked with `#[destruct(sensitive)]`. Combinators writing the value
+    /// out, like `print` or `Emit` of destruct-parser, hide such fields unless asked to reveal
+    /// them.
+    fn sensitive() -> bool {
+        false
+    }
+
+    /// The `key = "value"` pairs of `#[destruct(meta(...))]` on the field, for combinators to
+    /// interpret, such as `meta(len = "header.len")`.
+    fn meta() -> &'static [(&'static str, &'static str)] {
+        &[]
+    }
 }
 
 impl<T, M: DestructMetadata + 'static> DestructBegin<T, M> {
@@ -63,6 +101,12 @@ impl<T, M: DestructMetadata + 'static> DestructBegin<T, M> {
     }
 }
 
+impl<T: Clone, M: DestructMetadata + 'static> Clone for DestructBegin<T, M> {
+    fn clone(&self) -> Self {
+        DestructBegin::new(self.fields.clone())
+    }
+}
+
 #[derive(new, Debug, PartialEq, Eq)]
 pub struct DestructField<H, T, M: DestructFieldMetadata + 'static> {
     pub head: H,
@@ -81,6 +125,18 @@ impl<H, T, M: DestructFieldMetadata + 'static> DestructField<H, T, M> {
     pub fn field_index(&self) -> usize {
         M::field_index()
     }
+    pub fn sensitive(&self) -> bool {
+        M::sensitive()
+    }
+    pub fn meta(&self) -> &'static [(&'static str, &'static str)] {
+        M::meta()
+    }
+}
+
+impl<H: Clone, T: Clone, M: DestructFieldMetadata + 'static> Clone for DestructField<H, T, M> {
+    fn clone(&self) -> Self {
+        DestructField::new(self.head.clone(), self.tail.clone())
+    }
 }
 
 #[derive(new, Debug, PartialEq, Eq)]
@@ -95,6 +151,12 @@ impl<M: DestructMetadata + 'static> DestructEnd<M> {
     }
 }
 
+impl<M: DestructMetadata + 'static> Clone for DestructEnd<M> {
+    fn clone(&self) -> Self {
+        DestructEnd::new()
+    }
+}
+
 pub trait DestructEnumMetadata {
     fn enum_name() -> &'static str;
 }
@@ -109,6 +171,12 @@ pub struct DestructEnumBegin<T, M: DestructEnumMetadata + 'static> {
 pub trait DestructEnumVariantMetadata: DestructEnumMetadata + 'static {
     fn variant_name() -> &'static str;
     fn variant_index() -> usize;
+
+    /// The discriminant of the variant, as Rust assigns it: the explicit `= value` if any,
+    /// otherwise one more than the previous variant's.
+    fn discriminant() -> i128 {
+        Self::variant_index() as i128
+    }
 }
 
 impl<T, M: DestructEnumMetadata + 'static> DestructEnumBegin<T, M> {
@@ -117,6 +185,12 @@ impl<T, M: DestructEnumMetadata + 'static> DestructEnumBegin<T, M> {
     }
 }
 
+impl<T: Clone, M: DestructEnumMetadata + 'static> Clone for DestructEnumBegin<T, M> {
+    fn clone(&self) -> Self {
+        DestructEnumBegin::new(self.variants.clone())
+    }
+}
+
 #[derive(new, Debug, PartialEq, Eq)]
 pub enum DestructEnumVariant<H, T, M: DestructEnumVariantMetadata + 'static> {
     Head(H, #[new(default)] PhantomData<&'static M>),
@@ -133,6 +207,20 @@ impl<H, T, M: DestructEnumVariantMetadata + 'static> DestructEnumVariant<H, T, M
     pub fn variant_index() -> usize {
         M::variant_index()
     }
+    pub fn discriminant() -> i128 {
+        M::discriminant()
+    }
+}
+
+impl<H: Clone, T: Clone, M: DestructEnumVariantMetadata + 'static> Clone
+    for DestructEnumVariant<H, T, M>
+{
+    fn clone(&self) -> Self {
+        match self {
+            DestructEnumVariant::Head(head, _) => DestructEnumVariant::new_head(head.clone()),
+            DestructEnumVariant::Tail(tail, _) => DestructEnumVariant::new_tail(tail.clone()),
+        }
+    }
 }
 
 #[derive(new, Debug, PartialEq, Eq)]
@@ -147,7 +235,14 @@ impl<M: DestructEnumMetadata + 'static> DestructEnumEnd<M> {
     }
 }
 
+impl<M: DestructEnumMetadata + 'static> Clone for DestructEnumEnd<M> {
+    fn clone(&self) -> Self {
+        DestructEnumEnd::new()
+    }
+}
+
 #[cfg(test)]
+#[allow(non_local_definitions)]
 mod tests {
     use super::*;
     use crate::tests::ParseError::IOError;
@@ -174,6 +269,7 @@ mod tests {
         }
     }
 
+    #[allow(dead_code)]
     struct AA();
 
     /// Test for simple bincode
@@ -284,4 +380,37 @@ mod tests {
         assert_eq!(e, TestEnum::B);
     }
 
+    /// Variant structs holding a private type must not be more public than the enum.
+    #[derive(Debug, Destruct, PartialEq, Eq)]
+    enum Tree {
+        Leaf,
+        Node(Box<Tree>),
+    }
+
+    #[test]
+    fn test_private_enum() {
+        let tree = Tree::Node(Box::new(Tree::Leaf));
+        let e = Tree::construct(Tree::Node(Box::new(Tree::Leaf)).destruct());
+        assert_eq!(e, tree);
+    }
+
+    #[derive(Debug, Destruct, PartialEq, Eq)]
+    enum Borrowing<'a> {
+        Owned(u8),
+        Borrowed { name: &'a str },
+    }
+
+    #[test]
+    fn test_lifetime() {
+        let name = String::from("name");
+        let e = Borrowing::Borrowed { name: &name };
+        let d = e.destruct_ref();
+        assert!(matches!(d.variants, DestructEnumVariant::Tail(_, _)));
+        let e = Borrowing::construct(Borrowing::Borrowed { name: &name }.destruct());
+        assert_eq!(e, Borrowing::Borrowed { name: "name" });
+        assert_eq!(
+            Borrowing::construct(Borrowing::Owned(1).destruct()),
+            Borrowing::Owned(1)
+        );
+    }
 }
@@ -0,0 +1,692 @@
+//! Access fields at runtime by a dotted path such as `"db.pool.size"`.
+//!
+//! Segments are matched against `DestructFieldMetadata::field_name` for structs with named fields,
+//! and against `DestructFieldMetadata::field_index` for tuple structs (`"point.0"`). For enums the
+//! segments are looked up in the fields of the current variant.
+//!
+//! Implement `PathAccess` for a derived type with `path_access!` or `#[destruct(path_access)]`.
+//! Reads return `&dyn Any`, so a type with lifetimes only gets it when they are `'static`. A
+//! generic type implementing `Destruct` by hand lists its type parameters first, as in
+//! `path_access!(<T> Pair<T>)`.
+
+use crate::*;
+use std::any::Any;
+use std::borrow::Cow;
+use std::fmt;
+use std::str::FromStr;
+
+/// Get the field at `path`. An empty path returns `value` itself.
+pub fn get<'a, T: PathAccess>(value: &'a T, path: &str) -> Result<&'a dyn Any, PathError> {
+    let segments = split(path);
+    value.get_path(Segments::new(&segments))
+}
+
+/// Parse `input` into the type of the field at `path`, and store it there.
+pub fn set<T: PathAccess>(value: &mut T, path: &str, input: &str) -> Result<(), PathError> {
+    let segments = split(path);
+    value.set_path(Segments::new(&segments), input)
+}
+
+fn split(path: &str) -> Vec<&str> {
+    if path.is_empty() {
+        Vec::new()
+    } else {
+        path.split('.').collect()
+    }
+}
+
+/// The segments of a path, and how many of them have been followed.
+#[derive(Debug, Clone, Copy)]
+pub struct Segments<'p> {
+    all: &'p [&'p str],
+    position: usize,
+}
+
+impl<'p> Segments<'p> {
+    pub fn new(all: &'p [&'p str]) -> Segments<'p> {
+        Segments { all, position: 0 }
+    }
+
+    /// The segment to follow next.
+    pub fn first(&self) -> Option<&'p str> {
+        self.all.get(self.position).copied()
+    }
+
+    /// The segments after `first`.
+    pub fn rest(self) -> Segments<'p> {
+        Segments {
+            all: self.all,
+            position: self.position + 1,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.position >= self.all.len()
+    }
+
+    /// The path up to and including `first`, for error messages.
+    pub fn path(&self) -> String {
+        let end = (self.position + 1).min(self.all.len());
+        self.all[..end].join(".")
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PathError {
+    /// The last segment of `path` is not a field of `type_name`.
+    UnknownField {
+        path: String,
+        type_name: &'static str,
+    },
+    /// The last segment of `path` is not an index below `len`.
+    IndexOutOfRange { path: String, len: usize },
+    /// `path` names a value with fields, which can not be set from a string.
+    NotALeaf {
+        path: String,
+        type_name: &'static str,
+    },
+    /// `path` goes through an `Option` field that is `None`.
+    NoValue { path: String },
+    /// `value` can not be parsed into the type of the field at `path`.
+    InvalidValue {
+        path: String,
+        value: String,
+        message: String,
+    },
+}
+
+impl PathError {
+    pub fn unknown_field(segments: Segments, type_name: &'static str) -> PathError {
+        PathError::UnknownField {
+            path: segments.path(),
+            type_name,
+        }
+    }
+
+    pub fn not_a_leaf(segments: Segments, type_name: &'static str) -> PathError {
+        PathError::NotALeaf {
+            path: segments.all[..segments.position].join("."),
+            type_name,
+        }
+    }
+
+    pub fn no_value(segments: Segments) -> PathError {
+        PathError::NoValue {
+            path: segments.all[..segments.position].join("."),
+        }
+    }
+}
+
+impl fmt::Display for PathError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PathError::UnknownField { path, type_name } => {
+                let segment = path.rsplit('.').next().unwrap_or("");
+                write!(f, "no field `{}` in {} (at `{}`)", segment, type_name, path)
+            }
+            PathError::IndexOutOfRange { path, len } => {
+                write!(f, "index out of range for length {} (at `{}`)", len, path)
+            }
+            PathError::NotALeaf { path, type_name } => {
+                write!(f, "can not set {} from a string (at `{}`)", type_name, path)
+            }
+            PathError::NoValue { path } => write!(f, "no value at `{}`", path),
+            PathError::InvalidValue {
+                path,
+                value,
+                message,
+            } => write!(f, "invalid value {:?} for `{}`: {}", value, path, message),
+        }
+    }
+}
+
+impl std::error::Error for PathError {}
+
+pub trait PathAccess {
+    /// Follow `segments` and return the value at the end.
+    fn get_path<'a>(&'a self, segments: Segments) -> Result<&'a dyn Any, PathError>;
+
+    /// Follow `segments` and parse `value` into the field at the end.
+    fn set_path(&mut self, segments: Segments, value: &str) -> Result<(), PathError>;
+}
+
+macro_rules! path_access_leaf {
+    ($($t:ty),*) => {
+        $(
+            impl PathAccess for $t {
+                fn get_path<'a>(&'a self, segments: Segments) -> Result<&'a dyn Any, PathError> {
+                    if segments.is_empty() {
+                        Ok(self)
+                    } else {
+                        Err(PathError::unknown_field(segments, stringify!($t)))
+                    }
+                }
+
+                fn set_path(&mut self, segments: Segments, value: &str) -> Result<(), PathError> {
+                    if !segments.is_empty() {
+                        return Err(PathError::unknown_field(segments, stringify!($t)));
+                    }
+                    *self = <$t as FromStr>::from_str(value).map_err(|e| PathError::InvalidValue {
+                        path: segments.all.join("."),
+                        value: value.to_string(),
+                        message: e.to_string(),
+                    })?;
+                    Ok(())
+                }
+            }
+        )*
+    };
+}
+
+path_access_leaf!(
+    bool, char, String, u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize, f32, f64
+);
+
+impl PathAccess for Cow<'static, str> {
+    fn get_path<'a>(&'a self, segments: Segments) -> Result<&'a dyn Any, PathError> {
+        if segments.is_empty() {
+            Ok(self)
+        } else {
+            Err(PathError::unknown_field(segments, "Cow<str>"))
+        }
+    }
+
+    fn set_path(&mut self, segments: Segments, value: &str) -> Result<(), PathError> {
+        if !segments.is_empty() {
+            return Err(PathError::unknown_field(segments, "Cow<str>"));
+        }
+        *self = Cow::Owned(value.to_string());
+        Ok(())
+    }
+}
+
+impl<T: PathAccess> PathAccess for Box<T> {
+    fn get_path<'a>(&'a self, segments: Segments) -> Result<&'a dyn Any, PathError> {
+        (**self).get_path(segments)
+    }
+
+    fn set_path(&mut self, segments: Segments, value: &str) -> Result<(), PathError> {
+        (**self).set_path(segments, value)
+    }
+}
+
+/// The path goes on into the value if there is one. An empty path returns the `Option` itself.
+impl<T: PathAccess + 'static> PathAccess for Option<T> {
+    fn get_path<'a>(&'a self, segments: Segments) -> Result<&'a dyn Any, PathError> {
+        match self {
+            _ if segments.is_empty() => Ok(self),
+            Some(value) => value.get_path(segments),
+            None => Err(PathError::no_value(segments)),
+        }
+    }
+
+    fn set_path(&mut self, segments: Segments, value: &str) -> Result<(), PathError> {
+        match self {
+            Some(inner) => inner.set_path(segments, value),
+            None => Err(PathError::no_value(segments)),
+        }
+    }
+}
+
+/// Elements are addressed by their index, as in `"servers.2.port"`.
+impl<T: PathAccess + 'static> PathAccess for Vec<T> {
+    fn get_path<'a>(&'a self, segments: Segments) -> Result<&'a dyn Any, PathError> {
+        match segments.first() {
+            None => Ok(self),
+            Some(segment) => self[index(self.len(), segments, segment)?].get_path(segments.rest()),
+        }
+    }
+
+    fn set_path(&mut self, segments: Segments, value: &str) -> Result<(), PathError> {
+        match segments.first() {
+            None => Err(PathError::not_a_leaf(segments, "Vec")),
+            Some(segment) => {
+                let index = index(self.len(), segments, segment)?;
+                self[index].set_path(segments.rest(), value)
+            }
+        }
+    }
+}
+
+fn index(len: usize, segments: Segments, segment: &str) -> Result<usize, PathError> {
+    match segment.parse::<usize>() {
+        Ok(index) if index < len => Ok(index),
+        Ok(_) => Err(PathError::IndexOutOfRange {
+            path: segments.path(),
+            len,
+        }),
+        Err(_) => Err(PathError::unknown_field(segments, "Vec")),
+    }
+}
+
+/// Whether `segment` names the field described by `M`.
+fn matches_field<M: DestructFieldMetadata>(segment: &str) -> bool {
+    if M::named_fields() {
+        segment == M::field_name()
+    } else {
+        segment.parse::<usize>().ok() == Some(M::field_index())
+    }
+}
+
+/// Field lookup over the output of `DestructRef::destruct_ref`.
+pub trait FieldsRef<'a> {
+    fn get_field(self, segments: Segments) -> Result<&'a dyn Any, PathError>;
+}
+
+impl<'a, M: DestructMetadata + 'static> FieldsRef<'a> for DestructEnd<M> {
+    fn get_field(self, segments: Segments) -> Result<&'a dyn Any, PathError> {
+        Err(PathError::unknown_field(segments, M::struct_name()))
+    }
+}
+
+impl<'a, H: PathAccess, T: FieldsRef<'a>, M: DestructFieldMetadata + 'static> FieldsRef<'a>
+    for DestructField<&'a H, T, M>
+{
+    fn get_field(self, segments: Segments) -> Result<&'a dyn Any, PathError> {
+        match segments.first() {
+            Some(segment) if matches_field::<M>(segment) => self.head.get_path(segments.rest()),
+            _ => self.tail.get_field(segments),
+        }
+    }
+}
+
+impl<'a, F: FieldsRef<'a>, M: DestructMetadata + 'static> FieldsRef<'a> for DestructBegin<F, M> {
+    fn get_field(self, segments: Segments) -> Result<&'a dyn Any, PathError> {
+        self.fields.get_field(segments)
+    }
+}
+
+impl<'a, M: DestructEnumMetadata + 'static> FieldsRef<'a> for DestructEnumEnd<M> {
+    fn get_field(self, _: Segments) -> Result<&'a dyn Any, PathError> {
+        unreachable!("enum {} has no variants", M::enum_name())
+    }
+}
+
+impl<'a, H: FieldsRef<'a>, T: FieldsRef<'a>, M: DestructEnumVariantMetadata + 'static> FieldsRef<'a>
+    for DestructEnumVariant<H, T, M>
+{
+    fn get_field(self, segments: Segments) -> Result<&'a dyn Any, PathError> {
+        match self {
+            DestructEnumVariant::Head(head, _) => head.get_field(segments),
+            DestructEnumVariant::Tail(tail, _) => tail.get_field(segments),
+        }
+    }
+}
+
+impl<'a, T: FieldsRef<'a>, M: DestructEnumMetadata + 'static> FieldsRef<'a>
+    for DestructEnumBegin<T, M>
+{
+    fn get_field(self, segments: Segments) -> Result<&'a dyn Any, PathError> {
+        self.variants.get_field(segments)
+    }
+}
+
+/// Field lookup over the output of `DestructMut::destruct_mut`.
+pub trait FieldsMut {
+    fn set_field(self, segments: Segments, value: &str) -> Result<(), PathError>;
+}
+
+impl<M: DestructMetadata + 'static> FieldsMut for DestructEnd<M> {
+    fn set_field(self, segments: Segments, _: &str) -> Result<(), PathError> {
+        Err(PathError::unknown_field(segments, M::struct_name()))
+    }
+}
+
+impl<H: PathAccess, T: FieldsMut, M: DestructFieldMetadata + 'static> FieldsMut
+    for DestructField<&mut H, T, M>
+{
+    fn set_field(self, segments: Segments, value: &str) -> Result<(), PathError> {
+        match segments.first() {
+            Some(segment) if matches_field::<M>(segment) => {
+                self.head.set_path(segments.rest(), value)
+            }
+            _ => self.tail.set_field(segments, value),
+        }
+    }
+}
+
+impl<F: FieldsMut, M: DestructMetadata + 'static> FieldsMut for DestructBegin<F, M> {
+    fn set_field(self, segments: Segments, value: &str) -> Result<(), PathError> {
+        self.fields.set_field(segments, value)
+    }
+}
+
+impl<M: DestructEnumMetadata + 'static> FieldsMut for DestructEnumEnd<M> {
+    fn set_field(self, _: Segments, _: &str) -> Result<(), PathError> {
+        unreachable!("enum {} has no variants", M::enum_name())
+    }
+}
+
+impl<H: FieldsMut, T: FieldsMut, M: DestructEnumVariantMetadata + 'static> FieldsMut
+    for DestructEnumVariant<H, T, M>
+{
+    fn set_field(self, segments: Segments, value: &str) -> Result<(), PathError> {
+        match self {
+            DestructEnumVariant::Head(head, _) => head.set_field(segments, value),
+            DestructEnumVariant::Tail(tail, _) => tail.set_field(segments, value),
+        }
+    }
+}
+
+impl<T: FieldsMut, M: DestructEnumMetadata + 'static> FieldsMut for DestructEnumBegin<T, M> {
+    fn set_field(self, segments: Segments, value: &str) -> Result<(), PathError> {
+        self.variants.set_field(segments, value)
+    }
+}
+
+/// Use macros to workaround overlapping impls
+#[macro_export]
+macro_rules! path_access {
+    (< $($g:ident),+ > $t:ty) => {
+        impl<$($g: 'static),+> $crate::path::PathAccess for $t
+        where
+            for<'d> $t: $crate::DestructRef<'d> + $crate::DestructMut<'d>,
+            for<'d> <$t as $crate::DestructRef<'d>>::DestructRefType: $crate::path::FieldsRef<'d>,
+            for<'d> <$t as $crate::DestructMut<'d>>::DestructMutType: $crate::path::FieldsMut,
+        {
+            $crate::path_access!(@methods $t);
+        }
+    };
+    ($t:ident < $($lt:lifetime),+ >) => {
+        impl<$($lt),+> $crate::path::PathAccess for $t<$($lt),+>
+        where
+            Self: 'static,
+        {
+            $crate::path_access!(@methods $t<$($lt),+>);
+        }
+    };
+    ($t:ty) => {
+        impl $crate::path::PathAccess for $t {
+            $crate::path_access!(@methods $t);
+        }
+    };
+    (@methods $t:ty) => {
+        fn get_path<'path>(
+            &'path self,
+            segments: $crate::path::Segments,
+        ) -> Result<&'path dyn std::any::Any, $crate::path::PathError> {
+            if segments.is_empty() {
+                return Ok(self);
+            }
+            $crate::path::FieldsRef::get_field(
+                <$t as $crate::DestructRef>::destruct_ref(self),
+                segments,
+            )
+        }
+
+        fn set_path(
+            &mut self,
+            segments: $crate::path::Segments,
+            value: &str,
+        ) -> Result<(), $crate::path::PathError> {
+            if segments.is_empty() {
+                return Err($crate::path::PathError::not_a_leaf(
+                    segments,
+                    stringify!($t),
+                ));
+            }
+            $crate::path::FieldsMut::set_field(
+                <$t as $crate::DestructMut>::destruct_mut(self),
+                segments,
+                value,
+            )
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate as destruct;
+
+    #[derive(Debug, Destruct, PartialEq)]
+    #[destruct(path_access)]
+    struct Config {
+        name: String,
+        db: Db,
+        servers: Vec<Server>,
+        backup: Option<Box<Pool>>,
+    }
+
+    #[derive(Debug, Destruct, PartialEq)]
+    #[destruct(path_access)]
+    struct Db {
+        pool: Pool,
+        timeout: f64,
+    }
+
+    #[derive(Debug, Destruct, PartialEq)]
+    #[destruct(path_access)]
+    struct Pool {
+        size: u32,
+    }
+
+    #[derive(Debug, Destruct, PartialEq)]
+    #[destruct(path_access)]
+    struct Server(String, u16);
+
+    #[derive(Debug, Destruct, PartialEq)]
+    #[destruct(path_access)]
+    struct Label<'a> {
+        text: Cow<'a, str>,
+    }
+
+    /// A generic type, implementing `Destruct` by hand as the derive does not support it.
+    #[derive(Debug, PartialEq)]
+    struct Pair<T> {
+        first: T,
+        second: T,
+    }
+
+    struct PairMeta;
+    struct FirstMeta;
+    struct SecondMeta;
+
+    impl DestructMetadata for PairMeta {
+        fn struct_name() -> &'static str {
+            "Pair"
+        }
+        fn named_fields() -> bool {
+            true
+        }
+    }
+
+    macro_rules! pair_field {
+        ($meta:ident, $name:expr, $index:expr) => {
+            impl DestructMetadata for $meta {
+                fn struct_name() -> &'static str {
+                    "Pair"
+                }
+                fn named_fields() -> bool {
+                    true
+                }
+            }
+            impl DestructFieldMetadata for $meta {
+                fn field_name() -> &'static str {
+                    $name
+                }
+                fn field_index() -> usize {
+                    $index
+                }
+            }
+        };
+    }
+
+    pair_field!(FirstMeta, "first", 0);
+    pair_field!(SecondMeta, "second", 1);
+
+    type PairFields<T> =
+        DestructField<T, DestructField<T, DestructEnd<PairMeta>, SecondMeta>, FirstMeta>;
+
+    impl<T> From<Pair<T>> for DestructBegin<PairFields<T>, PairMeta> {
+        fn from(pair: Pair<T>) -> Self {
+            let second = DestructField::new(pair.second, DestructEnd::new());
+            DestructBegin::new(DestructField::new(pair.first, second))
+        }
+    }
+
+    impl<T> From<DestructBegin<PairFields<T>, PairMeta>> for Pair<T> {
+        fn from(d: DestructBegin<PairFields<T>, PairMeta>) -> Self {
+            Pair {
+                first: d.fields.head,
+                second: d.fields.tail.head,
+            }
+        }
+    }
+
+    impl<T> Destruct for Pair<T> {
+        type DestructType = DestructBegin<PairFields<T>, PairMeta>;
+
+        fn destruct(self) -> Self::DestructType {
+            self.into()
+        }
+
+        fn construct(d: Self::DestructType) -> Self {
+            d.into()
+        }
+    }
+
+    impl<'d, T: 'd> DestructRef<'d> for Pair<T> {
+        type DestructRefType = DestructBegin<PairFields<&'d T>, PairMeta>;
+
+        fn destruct_ref(&'d self) -> Self::DestructRefType {
+            let second = DestructField::new(&self.second, DestructEnd::new());
+            DestructBegin::new(DestructField::new(&self.first, second))
+        }
+    }
+
+    impl<'d, T: 'd> DestructMut<'d> for Pair<T> {
+        type DestructMutType = DestructBegin<PairFields<&'d mut T>, PairMeta>;
+
+        fn destruct_mut(&'d mut self) -> Self::DestructMutType {
+            let second = DestructField::new(&mut self.second, DestructEnd::new());
+            DestructBegin::new(DestructField::new(&mut self.first, second))
+        }
+    }
+
+    path_access!(<T> Pair<T>);
+
+    #[derive(Debug, Destruct, PartialEq)]
+    #[destruct(path_access)]
+    enum Auth {
+        Token { token: String },
+        Basic(String, String),
+    }
+
+    fn config() -> Config {
+        Config {
+            name: "main".to_string(),
+            db: Db {
+                pool: Pool { size: 8 },
+                timeout: 1.5,
+            },
+            servers: vec![Server("a".to_string(), 80), Server("b".to_string(), 81)],
+            backup: None,
+        }
+    }
+
+    #[test]
+    fn test_get() {
+        let config = config();
+        let size = get(&config, "db.pool.size").unwrap();
+        assert_eq!(size.downcast_ref::<u32>(), Some(&8));
+        let pool = get(&config, "db.pool").unwrap();
+        assert_eq!(pool.downcast_ref::<Pool>(), Some(&Pool { size: 8 }));
+        let port = get(&config, "servers.1.1").unwrap();
+        assert_eq!(port.downcast_ref::<u16>(), Some(&81));
+        let root = get(&config, "").unwrap();
+        assert!(root.is::<Config>());
+    }
+
+    #[test]
+    fn test_set() {
+        let mut config = config();
+        set(&mut config, "db.pool.size", "32").unwrap();
+        set(&mut config, "db.timeout", "0.25").unwrap();
+        set(&mut config, "servers.0.0", "c").unwrap();
+        assert_eq!(config.db.pool.size, 32);
+        assert_eq!(config.db.timeout, 0.25);
+        assert_eq!(config.servers[0], Server("c".to_string(), 80));
+    }
+
+    #[test]
+    fn test_option() {
+        let mut config = config();
+        let backup = get(&config, "backup").unwrap();
+        assert_eq!(backup.downcast_ref::<Option<Box<Pool>>>(), Some(&None));
+        let e = set(&mut config, "backup.size", "4").unwrap_err();
+        assert_eq!(e.to_string(), "no value at `backup`");
+
+        config.backup = Some(Box::new(Pool { size: 1 }));
+        set(&mut config, "backup.size", "4").unwrap();
+        let size = get(&config, "backup.size").unwrap();
+        assert_eq!(size.downcast_ref::<u32>(), Some(&4));
+    }
+
+    #[test]
+    fn test_generic() {
+        let mut label = Label {
+            text: Cow::Borrowed("old"),
+        };
+        set(&mut label, "text", "new").unwrap();
+        assert_eq!(label.text, "new");
+
+        let mut pair = Pair {
+            first: Pool { size: 1 },
+            second: Pool { size: 2 },
+        };
+        set(&mut pair, "second.size", "3").unwrap();
+        let size = get(&pair, "second.size").unwrap();
+        assert_eq!(size.downcast_ref::<u32>(), Some(&3));
+        let e = get(&pair, "third").unwrap_err();
+        assert_eq!(e.to_string(), "no field `third` in Pair (at `third`)");
+    }
+
+    #[test]
+    fn test_enum() {
+        let mut auth = Auth::Token {
+            token: "secret".to_string(),
+        };
+        set(&mut auth, "token", "other").unwrap();
+        let token = get(&auth, "token").unwrap();
+        assert_eq!(token.downcast_ref::<String>().unwrap(), "other");
+
+        let auth = Auth::Basic("user".to_string(), "password".to_string());
+        let user = get(&auth, "0").unwrap();
+        assert_eq!(user.downcast_ref::<String>().unwrap(), "user");
+        assert_eq!(
+            get(&auth, "token").unwrap_err(),
+            PathError::UnknownField {
+                path: "token".to_string(),
+                type_name: "Auth::Basic",
+            }
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        let mut config = config();
+        let e = get(&config, "db.poool.size").unwrap_err();
+        assert_eq!(e.to_string(), "no field `poool` in Db (at `db.poool`)");
+        let e = get(&config, "db.pool.size.bits").unwrap_err();
+        assert_eq!(
+            e.to_string(),
+            "no field `bits` in u32 (at `db.pool.size.bits`)"
+        );
+        let e = get(&config, "servers.5").unwrap_err();
+        assert_eq!(
+            e.to_string(),
+            "index out of range for length 2 (at `servers.5`)"
+        );
+        let e = set(&mut config, "db.pool", "1").unwrap_err();
+        assert_eq!(
+            e.to_string(),
+            "can not set Pool from a string (at `db.pool`)"
+        );
+        let e = set(&mut config, "db.pool.size", "-1").unwrap_err();
+        assert_eq!(
+            e.to_string(),
+            "invalid value \"-1\" for `db.pool.size`: invalid digit found in string"
+        );
+    }
+}
@@ -0,0 +1,416 @@
+//! Print values from their metadata, hiding fields marked with `#[destruct(sensitive)]`.
+//!
+//! `display` writes a value the way `Debug` would, with sensitive fields masked as `***`. `pairs`
+//! flattens a value into `path=value` pairs (paths as understood by `destruct::path`), leaving
+//! sensitive fields out. Both reveal sensitive fields only when asked to with
+//! `Redaction::Reveal`.
+//!
+//! Implement `Printable` for a derived type with `printable!` or `#[destruct(printable)]`.
+
+use crate::*;
+use std::fmt;
+
+/// The text written in place of a sensitive value.
+pub const MASK: &str = "***";
+
+/// What to do with sensitive fields.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Redaction {
+    /// Mask them when printing, omit them from pairs.
+    #[default]
+    Redact,
+    /// Treat them like any other field.
+    Reveal,
+}
+
+pub trait Printable {
+    /// Write `self` in a `Debug`-like form.
+    fn print(&self, out: &mut dyn fmt::Write, redaction: Redaction) -> fmt::Result;
+
+    /// Push a `path=value` pair for `self`, or for each of its fields if it has any.
+    fn pairs(&self, path: &str, redaction: Redaction, pairs: &mut Vec<(String, String)>) {
+        let mut value = String::new();
+        // Writing to a String never fails.
+        let _ = self.print(&mut value, redaction);
+        pairs.push((path.to_string(), value));
+    }
+}
+
+/// Print `value` with sensitive fields masked. Use `reveal` to print them.
+pub fn display<T: Printable>(value: &T) -> Printed<'_, T> {
+    Printed {
+        value,
+        redaction: Redaction::Redact,
+    }
+}
+
+/// Flatten `value` into `path=value` pairs, leaving sensitive fields out.
+pub fn pairs<T: Printable>(value: &T) -> Vec<(String, String)> {
+    pairs_with(value, Redaction::Redact)
+}
+
+pub fn pairs_with<T: Printable>(value: &T, redaction: Redaction) -> Vec<(String, String)> {
+    let mut pairs = Vec::new();
+    value.pairs("", redaction, &mut pairs);
+    pairs
+}
+
+/// `Display` and `Debug` for a `Printable` value.
+pub struct Printed<'a, T> {
+    value: &'a T,
+    redaction: Redaction,
+}
+
+impl<'a, T> Printed<'a, T> {
+    /// Print sensitive fields as well.
+    pub fn reveal(mut self) -> Self {
+        self.redaction = Redaction::Reveal;
+        self
+    }
+}
+
+impl<'a, T: Printable> fmt::Display for Printed<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.value.print(f, self.redaction)
+    }
+}
+
+impl<'a, T: Printable> fmt::Debug for Printed<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.value.print(f, self.redaction)
+    }
+}
+
+macro_rules! printable_leaf {
+    ($($t:ty),*) => {
+        $(
+            impl Printable for $t {
+                fn print(&self, out: &mut dyn fmt::Write, _: Redaction) -> fmt::Result {
+                    write!(out, "{:?}", self)
+                }
+            }
+        )*
+    };
+}
+
+printable_leaf!(bool, char, String, f32, f64, ());
+printable_leaf!(u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize);
+
+impl<T: Printable> Printable for Box<T> {
+    fn print(&self, out: &mut dyn fmt::Write, redaction: Redaction) -> fmt::Result {
+        (**self).print(out, redaction)
+    }
+
+    fn pairs(&self, path: &str, redaction: Redaction, pairs: &mut Vec<(String, String)>) {
+        (**self).pairs(path, redaction, pairs)
+    }
+}
+
+impl<T: Printable> Printable for Option<T> {
+    fn print(&self, out: &mut dyn fmt::Write, redaction: Redaction) -> fmt::Result {
+        match self {
+            None => out.write_str("None"),
+            Some(value) => {
+                out.write_str("Some(")?;
+                value.print(out, redaction)?;
+                out.write_str(")")
+            }
+        }
+    }
+
+    /// `None` produces no pairs.
+    fn pairs(&self, path: &str, redaction: Redaction, pairs: &mut Vec<(String, String)>) {
+        if let Some(value) = self {
+            value.pairs(path, redaction, pairs)
+        }
+    }
+}
+
+impl<T: Printable> Printable for Vec<T> {
+    fn print(&self, out: &mut dyn fmt::Write, redaction: Redaction) -> fmt::Result {
+        out.write_str("[")?;
+        for (i, item) in self.iter().enumerate() {
+            if i > 0 {
+                out.write_str(", ")?;
+            }
+            item.print(out, redaction)?;
+        }
+        out.write_str("]")
+    }
+
+    fn pairs(&self, path: &str, redaction: Redaction, pairs: &mut Vec<(String, String)>) {
+        for (i, item) in self.iter().enumerate() {
+            item.pairs(&join(path, &i.to_string()), redaction, pairs);
+        }
+    }
+}
+
+fn join(path: &str, segment: &str) -> String {
+    if path.is_empty() {
+        segment.to_string()
+    } else {
+        format!("{}.{}", path, segment)
+    }
+}
+
+/// Printing over the output of `DestructRef::destruct_ref`.
+pub trait PrintableFields {
+    /// Print the fields starting at position `index`, and close the list after the last one.
+    fn print_fields(
+        self,
+        out: &mut dyn fmt::Write,
+        index: usize,
+        redaction: Redaction,
+    ) -> fmt::Result;
+
+    fn field_pairs(self, path: &str, redaction: Redaction, pairs: &mut Vec<(String, String)>);
+}
+
+impl<M: DestructMetadata + 'static> PrintableFields for DestructEnd<M> {
+    fn print_fields(self, out: &mut dyn fmt::Write, index: usize, _: Redaction) -> fmt::Result {
+        match (index, M::named_fields()) {
+            (0, _) => Ok(()),
+            (_, true) => out.write_str(" }"),
+            (_, false) => out.write_str(")"),
+        }
+    }
+
+    fn field_pairs(self, _: &str, _: Redaction, _: &mut Vec<(String, String)>) {}
+}
+
+impl<H: Printable, T: PrintableFields, M: DestructFieldMetadata + 'static> PrintableFields
+    for DestructField<&H, T, M>
+{
+    fn print_fields(
+        self,
+        out: &mut dyn fmt::Write,
+        index: usize,
+        redaction: Redaction,
+    ) -> fmt::Result {
+        match (index, M::named_fields()) {
+            (0, true) => out.write_str(" { ")?,
+            (0, false) => out.write_str("(")?,
+            _ => out.write_str(", ")?,
+        }
+        if M::named_fields() {
+            write!(out, "{}: ", M::field_name())?;
+        }
+        if M::sensitive() && redaction == Redaction::Redact {
+            out.write_str(MASK)?;
+        } else {
+            self.head.print(out, redaction)?;
+        }
+        self.tail.print_fields(out, index + 1, redaction)
+    }
+
+    fn field_pairs(self, path: &str, redaction: Redaction, pairs: &mut Vec<(String, String)>) {
+        if !M::sensitive() || redaction == Redaction::Reveal {
+            let segment = if M::named_fields() {
+                M::field_name().to_string()
+            } else {
+                M::field_index().to_string()
+            };
+            self.head.pairs(&join(path, &segment), redaction, pairs);
+        }
+        self.tail.field_pairs(path, redaction, pairs)
+    }
+}
+
+impl<F: PrintableFields, M: DestructMetadata + 'static> PrintableFields for DestructBegin<F, M> {
+    fn print_fields(
+        self,
+        out: &mut dyn fmt::Write,
+        index: usize,
+        redaction: Redaction,
+    ) -> fmt::Result {
+        out.write_str(M::struct_name())?;
+        self.fields.print_fields(out, index, redaction)
+    }
+
+    fn field_pairs(self, path: &str, redaction: Redaction, pairs: &mut Vec<(String, String)>) {
+        self.fields.field_pairs(path, redaction, pairs)
+    }
+}
+
+impl<M: DestructEnumMetadata + 'static> PrintableFields for DestructEnumEnd<M> {
+    fn print_fields(self, _: &mut dyn fmt::Write, _: usize, _: Redaction) -> fmt::Result {
+        unreachable!("enum {} has no variants", M::enum_name())
+    }
+
+    fn field_pairs(self, _: &str, _: Redaction, _: &mut Vec<(String, String)>) {
+        unreachable!("enum {} has no variants", M::enum_name())
+    }
+}
+
+impl<H: PrintableFields, T: PrintableFields, M: DestructEnumVariantMetadata + 'static>
+    PrintableFields for DestructEnumVariant<H, T, M>
+{
+    fn print_fields(
+        self,
+        out: &mut dyn fmt::Write,
+        index: usize,
+        redaction: Redaction,
+    ) -> fmt::Result {
+        match self {
+            DestructEnumVariant::Head(head, _) => head.print_fields(out, index, redaction),
+            DestructEnumVariant::Tail(tail, _) => tail.print_fields(out, index, redaction),
+        }
+    }
+
+    fn field_pairs(self, path: &str, redaction: Redaction, pairs: &mut Vec<(String, String)>) {
+        match self {
+            DestructEnumVariant::Head(head, _) => head.field_pairs(path, redaction, pairs),
+            DestructEnumVariant::Tail(tail, _) => tail.field_pairs(path, redaction, pairs),
+        }
+    }
+}
+
+impl<T: PrintableFields, M: DestructEnumMetadata + 'static> PrintableFields
+    for DestructEnumBegin<T, M>
+{
+    fn print_fields(
+        self,
+        out: &mut dyn fmt::Write,
+        index: usize,
+        redaction: Redaction,
+    ) -> fmt::Result {
+        self.variants.print_fields(out, index, redaction)
+    }
+
+    fn field_pairs(self, path: &str, redaction: Redaction, pairs: &mut Vec<(String, String)>) {
+        self.variants.field_pairs(path, redaction, pairs)
+    }
+}
+
+/// Use macros to workaround overlapping impls
+#[macro_export]
+macro_rules! printable {
+    ($t:ident) => {
+        impl $crate::print::Printable for $t {
+            fn print(
+                &self,
+                out: &mut dyn std::fmt::Write,
+                redaction: $crate::print::Redaction,
+            ) -> std::fmt::Result {
+                $crate::print::PrintableFields::print_fields(
+                    <$t as $crate::DestructRef>::destruct_ref(self),
+                    out,
+                    0,
+                    redaction,
+                )
+            }
+
+            fn pairs(
+                &self,
+                path: &str,
+                redaction: $crate::print::Redaction,
+                pairs: &mut Vec<(String, String)>,
+            ) {
+                $crate::print::PrintableFields::field_pairs(
+                    <$t as $crate::DestructRef>::destruct_ref(self),
+                    path,
+                    redaction,
+                    pairs,
+                )
+            }
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate as destruct;
+
+    #[derive(Destruct)]
+    #[destruct(printable)]
+    struct Login {
+        user: String,
+        #[destruct(sensitive)]
+        password: String,
+        db: Db,
+    }
+
+    #[derive(Destruct)]
+    #[destruct(printable)]
+    struct Db {
+        hosts: Vec<String>,
+        port: u16,
+    }
+
+    #[derive(Destruct, Debug, PartialEq, Eq)]
+    #[destruct(printable)]
+    struct Key(u8, #[destruct(sensitive)] Vec<u8>);
+
+    #[derive(Destruct)]
+    #[destruct(printable)]
+    enum Auth {
+        Token {
+            #[destruct(sensitive)]
+            token: String,
+        },
+        Key(Key),
+        Anonymous,
+    }
+
+    fn login() -> Login {
+        Login {
+            user: "admin".to_string(),
+            password: "hunter2".to_string(),
+            db: Db {
+                hosts: vec!["a".to_string(), "b".to_string()],
+                port: 5432,
+            },
+        }
+    }
+
+    #[test]
+    fn test_display() {
+        let login = login();
+        assert_eq!(
+            display(&login).to_string(),
+            r#"Login { user: "admin", password: ***, db: Db { hosts: ["a", "b"], port: 5432 } }"#
+        );
+        assert_eq!(
+            display(&login).reveal().to_string(),
+            r#"Login { user: "admin", password: "hunter2", db: Db { hosts: ["a", "b"], port: 5432 } }"#
+        );
+        assert_eq!(format!("{:?}", display(&Key(1, vec![2, 3]))), "Key(1, ***)");
+    }
+
+    #[test]
+    fn test_enum() {
+        let token = Auth::Token {
+            token: "abc".to_string(),
+        };
+        assert_eq!(display(&token).to_string(), "Auth::Token { token: *** }");
+        let key = Auth::Key(Key(7, vec![1]));
+        assert_eq!(display(&key).to_string(), "Auth::Key(Key(7, ***))");
+        assert_eq!(display(&Auth::Anonymous).to_string(), "Auth::Anonymous");
+        assert_eq!(pairs(&key), vec![("0.0".to_string(), "7".to_string())]);
+    }
+
+    #[test]
+    fn test_pairs() {
+        let login = login();
+        let expected = |pairs: &[(&str, &str)]| -> Vec<(String, String)> {
+            pairs
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect()
+        };
+        assert_eq!(
+            pairs(&login),
+            expected(&[
+                ("user", "\"admin\""),
+                ("db.hosts.0", "\"a\""),
+                ("db.hosts.1", "\"b\""),
+                ("db.port", "5432"),
+            ])
+        );
+        assert_eq!(
+            pairs_with(&login, Redaction::Reveal)[1],
+            ("password".to_string(), "\"hunter2\"".to_string())
+        );
+    }
+}
@@ -0,0 +1,366 @@
+//! Fields smaller than a byte, packed together as in protocol headers.
+//!
+//! ```ignore
+//! #[derive(Destruct)]
+//! #[destruct(parsable)]
+//! struct Ipv4 {
+//!     version: Bits<4>,
+//!     ihl: Bits<4>,
+//!     dscp: Bits<6>,
+//!     ecn: Bits<2>,
+//!     total_len: u16,
+//! }
+//! ```
+//!
+//! Bits are read from the most significant bit of each byte first, or from the least significant
+//! with the `lsb_first` option, and written the same way. A struct must end on a byte boundary,
+//! and a byte-sized field can not start in the middle of a byte.
+
+use crate::{emit_buffered, Emit, Endian, Input, Output, Parsable, ParseError, Redaction, Source};
+use byteorder::ReadBytesExt;
+use failure::{Error, Fail};
+use std::io;
+use std::io::Write;
+
+/// Order the bits of a byte are read in, set with the `msb_first` and `lsb_first` options.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BitOrder {
+    /// Most significant bit first, as in network protocols.
+    #[default]
+    Msb,
+    Lsb,
+}
+
+/// An `N` bit unsigned number, with `N` up to 64.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Bits<const N: u32>(pub u64);
+
+/// A single bit.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Flag(pub bool);
+
+/// The byte bit fields are read from, and how many of its bits are left.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) struct BitCursor {
+    byte: u8,
+    left: u32,
+}
+
+impl BitCursor {
+    pub(crate) fn left(&self) -> u32 {
+        self.left
+    }
+}
+
+/// Bits of a byte were left unread, or unwritten, at the end of a struct or before a byte-sized
+/// field.
+#[derive(Debug, Fail)]
+#[fail(display = "{} bits left before the byte boundary", _0)]
+pub struct Unaligned(pub u32);
+
+#[derive(Debug, Fail)]
+#[fail(display = "{} does not fit in {} bits", value, bits)]
+pub struct BitsOverflow {
+    pub value: u64,
+    pub bits: u32,
+}
+
+impl<'a, S: Source<'a>> Input<S> {
+    /// Read `count` bits, up to 64, as an unsigned number. The first bit read is the most
+    /// significant with `BitOrder::Msb`, the least significant with `BitOrder::Lsb`.
+    pub fn read_bits(&mut self, count: u32) -> Result<u64, ParseError> {
+        let order = self.bit_order();
+        let mut value = 0;
+        let mut read = 0;
+        while read < count {
+            let mut cursor = self.bit_cursor();
+            if cursor.left == 0 {
+                cursor.byte = self.read_u8().map_err(|e| self.error(e))?;
+                cursor.left = 8;
+            }
+            let take = (count - read).min(cursor.left);
+            let mask = ((1u16 << take) - 1) as u8;
+            let chunk = match order {
+                BitOrder::Msb => (cursor.byte >> (cursor.left - take)) & mask,
+                BitOrder::Lsb => (cursor.byte >> (8 - cursor.left)) & mask,
+            };
+            value = match order {
+                BitOrder::Msb => (value << take) | u64::from(chunk),
+                BitOrder::Lsb => value | (u64::from(chunk) << read),
+            };
+            cursor.left -= take;
+            read += take;
+            self.set_bit_cursor(cursor);
+        }
+        Ok(value)
+    }
+
+    /// Fail unless the input is on a byte boundary.
+    pub fn check_aligned(&self) -> Result<(), ParseError> {
+        match self.bit_cursor().left {
+            0 => Ok(()),
+            left => Err(self.error(Unaligned(left))),
+        }
+    }
+}
+
+impl Output {
+    /// Write the low `count` bits of `value`, up to 64, the counterpart of `Input::read_bits`.
+    /// A byte is started when needed, its bits not written yet stay zero.
+    pub fn write_bits(&mut self, value: u64, count: u32) -> Result<(), Error> {
+        if count < 64 && value >> count != 0 {
+            return Err(BitsOverflow { value, bits: count }.into());
+        }
+        let order = self.bit_order();
+        let mut written = 0;
+        while written < count {
+            if self.bits_left() == 0 {
+                self.write_all(&[0])?;
+                self.set_bits_left(8);
+            }
+            let left = self.bits_left();
+            let take = (count - written).min(left);
+            let mask = ((1u16 << take) - 1) as u8;
+            let (chunk, shift) = match order {
+                BitOrder::Msb => (
+                    (value >> (count - written - take)) as u8 & mask,
+                    left - take,
+                ),
+                BitOrder::Lsb => ((value >> written) as u8 & mask, 8 - left),
+            };
+            let last = self.len() - 1;
+            self[last] |= chunk << shift;
+            self.set_bits_left(left - take);
+            written += take;
+        }
+        Ok(())
+    }
+
+    /// Fail unless the output is on a byte boundary.
+    pub fn check_aligned(&self) -> Result<(), Error> {
+        match self.bits_left() {
+            0 => Ok(()),
+            left => Err(Unaligned(left).into()),
+        }
+    }
+}
+
+impl<'a, const N: u32> Parsable<'a> for Bits<N> {
+    fn parse_input<S: Source<'a>>(input: &mut Input<S>) -> Result<Self, ParseError> {
+        const { assert!(N <= 64, "Bits<N> holds at most 64 bits") };
+        input.read_bits(N).map(Bits)
+    }
+
+    fn number(&self) -> Option<i128> {
+        Some(i128::from(self.0))
+    }
+}
+
+impl<'a> Parsable<'a> for Flag {
+    fn parse_input<S: Source<'a>>(input: &mut Input<S>) -> Result<Self, ParseError> {
+        input.read_bits(1).map(|bit| Flag(bit == 1))
+    }
+
+    fn number(&self) -> Option<i128> {
+        Some(i128::from(self.0))
+    }
+}
+
+impl<const N: u32> Emit for Bits<N> {
+    fn emit<W: io::Write>(&self, write: &mut W) -> Result<(), Error> {
+        emit_buffered(self, write, Endian::default())
+    }
+
+    fn emit_in_struct(
+        &self,
+        bytes: &mut Output,
+        _: Option<usize>,
+        _: Endian,
+        _: Redaction,
+    ) -> Result<(), Error> {
+        const { assert!(N <= 64, "Bits<N> holds at most 64 bits") };
+        bytes.write_bits(self.0, N)
+    }
+}
+
+impl Emit for Flag {
+    fn emit<W: io::Write>(&self, write: &mut W) -> Result<(), Error> {
+        emit_buffered(self, write, Endian::default())
+    }
+
+    fn emit_in_struct(
+        &self,
+        bytes: &mut Output,
+        _: Option<usize>,
+        _: Endian,
+        _: Redaction,
+    ) -> Result<(), Error> {
+        bytes.write_bits(u64::from(self.0), 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    #[derive(Destruct, Debug, PartialEq, Eq)]
+    #[destruct(parsable, emit)]
+    struct Ipv4 {
+        version: Bits<4>,
+        ihl: Bits<4>,
+        dscp: Bits<6>,
+        ecn: Bits<2>,
+        total_len: u16,
+        identification: u16,
+        reserved: Flag,
+        dont_fragment: Flag,
+        more_fragments: Flag,
+        fragment_offset: Bits<13>,
+        ttl: u8,
+    }
+
+    #[derive(Destruct, Debug, PartialEq, Eq)]
+    #[destruct(parsable(lsb_first), emit)]
+    struct Control {
+        ack: Flag,
+        kind: Bits<3>,
+        window: Bits<12>,
+    }
+
+    #[derive(Destruct, Debug, PartialEq, Eq)]
+    #[destruct(parsable, emit)]
+    struct Odd {
+        first: Bits<4>,
+        second: u8,
+    }
+
+    #[derive(Destruct, Debug, PartialEq, Eq)]
+    #[destruct(parsable, emit)]
+    struct Nibble {
+        value: Bits<4>,
+    }
+
+    #[test]
+    fn test_bits() {
+        let bytes = [0x45, 0xb8, 0x00, 0x54, 0x12, 0x34, 0x40, 0x00, 0x40];
+        let ipv4 = Ipv4::parse_slice(&mut &bytes[..]).unwrap();
+        assert_eq!((ipv4.version, ipv4.ihl), (Bits(4), Bits(5)));
+        assert_eq!((ipv4.dscp, ipv4.ecn), (Bits(0x2e), Bits(0)));
+        assert_eq!(ipv4.total_len, 0x54);
+        assert_eq!(
+            (ipv4.dont_fragment, ipv4.more_fragments),
+            (Flag(true), Flag(false))
+        );
+        assert_eq!(ipv4.fragment_offset, Bits(0));
+
+        let control = Control::parse_slice(&mut &[0b1010_0111, 0xcd][..]).unwrap();
+        assert_eq!(control.ack, Flag(true));
+        assert_eq!(control.kind, Bits(0b011));
+        assert_eq!(control.window, Bits(0xcda));
+        assert_eq!(to_bytes(&control).unwrap(), [0b1010_0111, 0xcd]);
+
+        let mut bytes = &[0xab, 0xcd][..];
+        assert_eq!(Bits::<12>::parse_slice(&mut bytes).unwrap(), Bits(0xabc));
+
+        // Marks keep the position within the byte.
+        let bytes = [0b1011_0110, 0b0100_0000];
+        let mut input = Input::new(SliceSource::new(&bytes));
+        assert_eq!(input.read_bits(3).unwrap(), 0b101);
+        let mark = input.mark();
+        assert_eq!(input.read_bits(7).unwrap(), 0b101_1001);
+        input.rewind(mark).unwrap();
+        assert_eq!(input.read_bits(5).unwrap(), 0b1_0110);
+    }
+
+    #[derive(Destruct, Debug, PartialEq, Eq)]
+    #[destruct(parsable, emit)]
+    struct Key {
+        kind: Bits<3>,
+        #[destruct(sensitive)]
+        secret: Bits<5>,
+    }
+
+    #[test]
+    fn test_emit_bits() {
+        let ipv4 = Ipv4 {
+            version: Bits(4),
+            ihl: Bits(5),
+            dscp: Bits(0x2e),
+            ecn: Bits(1),
+            total_len: 0x54,
+            identification: 0x1234,
+            reserved: Flag(false),
+            dont_fragment: Flag(true),
+            more_fragments: Flag(false),
+            fragment_offset: Bits(0x1fff),
+            ttl: 64,
+        };
+        let bytes = to_bytes(&ipv4).unwrap();
+        assert_eq!(
+            bytes,
+            [0x45, 0xb9, 0x00, 0x54, 0x12, 0x34, 0x5f, 0xff, 0x40]
+        );
+        assert_eq!(roundtrip(&ipv4).unwrap(), ipv4);
+
+        // Rewinding clears the bits written since the mark.
+        let mut output = Output::new();
+        output.write_bits(0b101, 3).unwrap();
+        let mark = output.mark();
+        output.write_bits(0b1_1111, 5).unwrap();
+        output.rewind(mark);
+        output.write_bits(0b0_0001, 5).unwrap();
+        assert_eq!(output.into_bytes(), [0b1010_0001]);
+
+        // Masking a sensitive bit field leaves the bits before it.
+        let key = Key {
+            kind: Bits(0b101),
+            secret: Bits(0b1_1011),
+        };
+        assert_eq!(to_bytes(&key).unwrap(), [0b1010_0000]);
+        assert_eq!(
+            to_bytes_with(&key, Redaction::Reveal).unwrap(),
+            [0b1011_1011]
+        );
+    }
+
+    #[test]
+    fn test_emit_bits_errors() {
+        let odd = Odd {
+            first: Bits(1),
+            second: 2,
+        };
+        assert_eq!(
+            to_bytes(&odd).unwrap_err().to_string(),
+            "4 bits left before the byte boundary"
+        );
+        let nibble = Nibble { value: Bits(1) };
+        assert_eq!(
+            to_bytes(&nibble).unwrap_err().to_string(),
+            "4 bits left before the byte boundary"
+        );
+        let nibble = Nibble { value: Bits(16) };
+        assert_eq!(
+            to_bytes(&nibble).unwrap_err().to_string(),
+            "16 does not fit in 4 bits"
+        );
+    }
+
+    #[test]
+    fn test_bits_errors() {
+        let error = Odd::parse_slice(&mut &[0xff, 0xff][..]).unwrap_err();
+        let error = error.downcast::<ParseError>().unwrap();
+        assert_eq!(error.path(), "Odd.second");
+        assert_eq!(error.offset(), 1);
+
+        let error = Nibble::parse_slice(&mut &[0xff][..]).unwrap_err();
+        let error = error.downcast::<ParseError>().unwrap();
+        assert_eq!(
+            error.to_string(),
+            "Nibble at offset 1: 4 bits left before the byte boundary"
+        );
+
+        let error = Ipv4::parse_slice(&mut &[0x45][..]).unwrap_err();
+        let error = error.downcast::<ParseError>().unwrap();
+        assert_eq!(error.path(), "Ipv4.dscp");
+    }
+}
@@ -0,0 +1,233 @@
+//! Types borrowing from the input, for parsing from a `SliceSource` without copying.
+//!
+//! ```ignore
+//! #[derive(Destruct)]
+//! #[destruct(parsable, emit)]
+//! struct Request<'a> {
+//!     method: Delimited<b' ', &'a str>,
+//!     tag: Take<4, &'a [u8]>,
+//!     body: &'a [u8],
+//! }
+//!
+//! let request = Request::parse_slice(&mut &bytes[..])?;
+//! ```
+//!
+//! `&'a [u8]` and `&'a str` alone take the rest of the input.
+
+use crate::{Emit, Input, Parsable, ParseError, Source};
+use failure::{Error, Fail};
+use std::io;
+use std::str;
+
+/// A value made of bytes borrowed from the input.
+pub trait Borrowed<'a>: Sized {
+    fn from_bytes(bytes: &'a [u8]) -> Result<Self, Error>;
+    fn as_bytes(&self) -> &[u8];
+}
+
+impl<'a> Borrowed<'a> for &'a [u8] {
+    fn from_bytes(bytes: &'a [u8]) -> Result<Self, Error> {
+        Ok(bytes)
+    }
+
+    fn as_bytes(&self) -> &[u8] {
+        self
+    }
+}
+
+impl<'a> Borrowed<'a> for &'a str {
+    fn from_bytes(bytes: &'a [u8]) -> Result<Self, Error> {
+        Ok(str::from_utf8(bytes)?)
+    }
+
+    fn as_bytes(&self) -> &[u8] {
+        str::as_bytes(self)
+    }
+}
+
+/// Build a `T` from `len` borrowed bytes. An invalid value fails at the offset of the first
+/// invalid byte when it is known, and at the start of the bytes otherwise.
+fn borrow<'a, T: Borrowed<'a>, S: Source<'a>>(
+    input: &mut Input<S>,
+    len: usize,
+) -> Result<T, ParseError> {
+    let start = input.offset();
+    let bytes = input.borrow(len)?;
+    T::from_bytes(bytes).map_err(|e| {
+        let offset = match e.downcast_ref::<str::Utf8Error>() {
+            Some(utf8) => start + utf8.valid_up_to(),
+            None => start,
+        };
+        ParseError::new(offset, e)
+    })
+}
+
+impl<'a> Parsable<'a> for &'a [u8] {
+    fn parse_input<S: Source<'a>>(input: &mut Input<S>) -> Result<Self, ParseError> {
+        let len = input.unread()?.len();
+        borrow(input, len)
+    }
+}
+
+impl<'a> Parsable<'a> for &'a str {
+    fn parse_input<S: Source<'a>>(input: &mut Input<S>) -> Result<Self, ParseError> {
+        let len = input.unread()?.len();
+        borrow(input, len)
+    }
+}
+
+impl Emit for [u8] {
+    fn emit<W: io::Write>(&self, write: &mut W) -> Result<(), Error> {
+        write.write_all(self)?;
+        Ok(())
+    }
+}
+
+impl Emit for str {
+    fn emit<W: io::Write>(&self, write: &mut W) -> Result<(), Error> {
+        write.write_all(self.as_bytes())?;
+        Ok(())
+    }
+}
+
+/// Exactly `N` bytes.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Take<const N: usize, T>(pub T);
+
+/// The bytes up to the first `D`. The delimiter is consumed but not part of the value.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Delimited<const D: u8, T>(pub T);
+
+#[derive(Debug, Fail)]
+#[fail(display = "expected {} bytes, got {}", expected, actual)]
+pub struct LengthMismatch {
+    pub expected: usize,
+    pub actual: usize,
+}
+
+#[derive(Debug, Fail)]
+#[fail(display = "delimiter {:#04x} not found", _0)]
+pub struct MissingDelimiter(pub u8);
+
+#[derive(Debug, Fail)]
+#[fail(display = "value contains its delimiter {:#04x}", _0)]
+pub struct DelimiterInValue(pub u8);
+
+impl<'a, const N: usize, T: Borrowed<'a>> Parsable<'a> for Take<N, T> {
+    fn parse_input<S: Source<'a>>(input: &mut Input<S>) -> Result<Self, ParseError> {
+        borrow(input, N).map(Take)
+    }
+}
+
+impl<'a, const D: u8, T: Borrowed<'a>> Parsable<'a> for Delimited<D, T> {
+    fn parse_input<S: Source<'a>>(input: &mut Input<S>) -> Result<Self, ParseError> {
+        let unread = input.unread()?;
+        let len = match unread.iter().position(|b| *b == D) {
+            Some(len) => len,
+            None => {
+                return Err(ParseError::new(
+                    input.offset() + unread.len(),
+                    MissingDelimiter(D),
+                ))
+            }
+        };
+        let value = borrow(input, len)?;
+        input.borrow(1)?;
+        Ok(Delimited(value))
+    }
+}
+
+impl<'a, const N: usize, T: Borrowed<'a>> Emit for Take<N, T> {
+    fn emit<W: io::Write>(&self, write: &mut W) -> Result<(), Error> {
+        let bytes = self.0.as_bytes();
+        if bytes.len() != N {
+            return Err(LengthMismatch {
+                expected: N,
+                actual: bytes.len(),
+            }
+            .into());
+        }
+        bytes.emit(write)
+    }
+}
+
+impl<'a, const D: u8, T: Borrowed<'a>> Emit for Delimited<D, T> {
+    fn emit<W: io::Write>(&self, write: &mut W) -> Result<(), Error> {
+        let bytes = self.0.as_bytes();
+        if bytes.contains(&D) {
+            return Err(DelimiterInValue(D).into());
+        }
+        bytes.emit(write)?;
+        D.emit(write)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    #[derive(Destruct, Debug, PartialEq, Eq)]
+    #[destruct(parsable, emit)]
+    struct Request<'a> {
+        method: Delimited<b' ', &'a str>,
+        tag: Take<4, &'a [u8]>,
+        length: u8,
+        body: &'a [u8],
+    }
+
+    #[derive(Destruct, Debug, PartialEq, Eq)]
+    #[destruct(parsable, emit)]
+    enum Frame<'a> {
+        Empty(Validated<u8, IsAsciiDigit>),
+        Data(Take<2, &'a str>),
+    }
+
+    #[test]
+    fn test_borrow() {
+        let bytes = b"GET abcd\x05hello";
+        let request = Request::parse_slice(&mut &bytes[..]).unwrap();
+        assert_eq!(request.method, Delimited("GET"));
+        assert_eq!(request.tag, Take(&b"abcd"[..]));
+        assert_eq!(request.length, 5);
+        assert_eq!(request.body, b"hello");
+        assert_eq!(request.body.as_ptr(), bytes[9..].as_ptr());
+        assert_eq!(to_bytes(&request).unwrap(), bytes);
+    }
+
+    #[test]
+    fn test_borrow_enum() {
+        let mut bytes = &b"hi7"[..];
+        assert_eq!(
+            Frame::parse_slice(&mut bytes).unwrap(),
+            Frame::Data(Take("hi"))
+        );
+        assert_eq!(
+            Frame::parse_slice(&mut bytes).unwrap(),
+            Frame::Empty(Validated::new(b'7'))
+        );
+        assert!(bytes.is_empty());
+    }
+
+    #[test]
+    fn test_borrow_errors() {
+        let error = Request::parse_slice(&mut &b"GET"[..]).unwrap_err();
+        let error = error.downcast::<ParseError>().unwrap();
+        assert_eq!(error.path(), "Request.method");
+        assert_eq!(error.offset(), 3);
+
+        let error = Request::parse_slice(&mut &b"G\xff \x01"[..]).unwrap_err();
+        let error = error.downcast::<ParseError>().unwrap();
+        assert_eq!(error.offset(), 1);
+
+        let error = Frame::parse(&mut &b"hi"[..]).unwrap_err();
+        assert!(error.to_string().contains("needs a SliceSource"));
+
+        let request = Request {
+            method: Delimited("G T"),
+            tag: Take(b"abcd"),
+            length: 0,
+            body: b"",
+        };
+        assert!(to_bytes(&request).is_err());
+    }
+}
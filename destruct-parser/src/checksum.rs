@@ -0,0 +1,314 @@
+//! Checksums over the fields before them in a struct.
+//!
+//! ```ignore
+//! #[derive(Destruct)]
+//! #[destruct(parsable(little_endian), emit)]
+//! struct Record {
+//!     kind: u8,
+//!     data: LengthPrefixed<u16, Vec<u8>>,
+//!     crc: Checksum<Crc32>,
+//! }
+//! ```
+//!
+//! Parsing fails with `ChecksumMismatch` if the stored checksum is not the one computed over the
+//! bytes of the preceding fields. Emitting writes the computed checksum, whatever `value` holds.
+
+use crate::{Emit, Endian, Input, Output, Parsable, ParseError, Redaction, Source};
+use failure::{Error, Fail};
+use std::fmt;
+use std::io::{self, Read};
+
+/// A checksum algorithm, such as `Crc32` or `Adler32`.
+pub trait Algorithm {
+    /// The checksum as stored, in the byte order of the struct.
+    type Value: Copy + PartialEq + Into<u64>;
+
+    fn name() -> &'static str;
+    fn compute(bytes: &[u8]) -> Self::Value;
+}
+
+/// The checksum by `A` of the fields before it in the enclosing struct.
+#[derive(new, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Checksum<A: Algorithm> {
+    pub value: A::Value,
+}
+
+/// CRC-32 as in zlib, PNG and Ethernet.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Crc32;
+
+/// Adler-32 as in zlib streams.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Adler32;
+
+#[derive(Debug, Fail)]
+pub struct ChecksumMismatch {
+    pub algorithm: &'static str,
+    pub stored: u64,
+    pub computed: u64,
+}
+
+/// The bytes of the enclosing struct are not available to `Input::struct_bytes` or to emitting a
+/// `Checksum`.
+#[derive(Debug, Fail)]
+pub enum NoStructBytes {
+    #[fail(display = "not in a struct")]
+    NoStruct,
+    #[fail(
+        display = "the enclosing struct does not keep its bytes, as no field sets NEEDS_STRUCT_BYTES"
+    )]
+    NotKept,
+}
+
+impl fmt::Display for ChecksumMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} checksum {:#010x} does not match {:#010x} computed",
+            self.algorithm, self.stored, self.computed
+        )
+    }
+}
+
+const CRC32_TABLE: [u32; 256] = {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+impl Algorithm for Crc32 {
+    type Value = u32;
+
+    fn name() -> &'static str {
+        "CRC-32"
+    }
+
+    fn compute(bytes: &[u8]) -> u32 {
+        let crc = bytes.iter().fold(!0, |crc: u32, byte| {
+            CRC32_TABLE[usize::from(crc as u8 ^ byte)] ^ (crc >> 8)
+        });
+        !crc
+    }
+}
+
+impl Algorithm for Adler32 {
+    type Value = u32;
+
+    fn name() -> &'static str {
+        "Adler-32"
+    }
+
+    fn compute(bytes: &[u8]) -> u32 {
+        const MOD: u32 = 65521;
+        let (a, b) = bytes.iter().fold((1, 0), |(a, b), byte| {
+            let a = (a + u32::from(*byte)) % MOD;
+            (a, (b + a) % MOD)
+        });
+        (b << 16) | a
+    }
+}
+
+impl<'a, S: Source<'a>> Input<S> {
+    /// The bytes of the struct being parsed, up to the current offset. Only available while
+    /// parsing a struct whose fields set `Parsable::NEEDS_STRUCT_BYTES`, otherwise fails with
+    /// `NoStructBytes`.
+    pub fn struct_bytes(&mut self) -> Result<Vec<u8>, ParseError> {
+        let end = self.offset();
+        let start = self
+            .dependencies()
+            .kept_struct_start()
+            .map_err(|e| self.error(e))?;
+        self.skip_to(start)?;
+        let mut bytes = vec![0; end - start];
+        self.read_exact(&mut bytes).map_err(|e| self.error(e))?;
+        Ok(bytes)
+    }
+}
+
+impl<'a, A: Algorithm> Parsable<'a> for Checksum<A>
+where
+    A::Value: Parsable<'a>,
+{
+    const NEEDS_STRUCT_BYTES: bool = true;
+
+    fn parse_input<S: Source<'a>>(input: &mut Input<S>) -> Result<Self, ParseError> {
+        let offset = input.offset();
+        let computed = A::compute(&input.struct_bytes()?);
+        let stored = A::Value::parse_input(input)?;
+        if stored == computed {
+            Ok(Checksum::new(stored))
+        } else {
+            let error = ChecksumMismatch {
+                algorithm: A::name(),
+                stored: stored.into(),
+                computed: computed.into(),
+            };
+            Err(ParseError::new(offset, error))
+        }
+    }
+}
+
+/// Outside of a struct, fails with `NoStructBytes` like parsing.
+impl<A: Algorithm> Emit for Checksum<A>
+where
+    A::Value: Emit,
+{
+    fn emit<W: io::Write>(&self, _: &mut W) -> Result<(), Error> {
+        Err(NoStructBytes::NoStruct.into())
+    }
+
+    fn emit_in_struct(
+        &self,
+        bytes: &mut Output,
+        start: Option<usize>,
+        order: Endian,
+        _: Redaction,
+    ) -> Result<(), Error> {
+        let start = start.ok_or(NoStructBytes::NoStruct)?;
+        let computed = A::compute(&bytes[start..]);
+        computed.emit_ordered(bytes, order)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    #[derive(Destruct, Debug, PartialEq, Eq)]
+    #[destruct(parsable, emit)]
+    struct Entry {
+        text: Exactly<9, u8>,
+        crc: Checksum<Crc32>,
+    }
+
+    #[derive(Destruct, Debug, PartialEq, Eq)]
+    #[destruct(parsable(little_endian), emit)]
+    struct Note {
+        len: u8,
+        #[destruct(meta(count = "len"))]
+        text: Vec<u8>,
+        sum: Checksum<Adler32>,
+        entry: Entry,
+    }
+
+    #[test]
+    fn test_checksum() {
+        let bytes = b"123456789\xcb\xf4\x39\x26";
+        let entry = Entry::parse(&mut &bytes[..]).unwrap();
+        assert_eq!(entry.crc.value, 0xcbf4_3926);
+        assert_eq!(Adler32::compute(b"Wikipedia"), 0x11e6_0398);
+
+        let note = Note {
+            len: 9,
+            text: b"Wikipedia".to_vec(),
+            sum: Checksum::new(0),
+            entry: Entry {
+                text: Exactly(b"123456789".to_vec()),
+                crc: Checksum::new(0),
+            },
+        };
+        let mut expected = b"\x09Wikipedia".to_vec();
+        expected.extend_from_slice(&Adler32::compute(&expected).to_le_bytes());
+        // `Entry` covers its own bytes, in the byte order of `Note`.
+        expected.extend_from_slice(b"123456789\x26\x39\xf4\xcb");
+        assert_eq!(to_bytes(&note).unwrap(), expected);
+        let parsed = Note::parse(&mut &expected[..]).unwrap();
+        assert_eq!(parsed.sum.value, Adler32::compute(b"\x09Wikipedia"));
+        assert_eq!(parsed.entry.crc, entry.crc);
+    }
+
+    #[derive(Destruct, Debug, PartialEq, Eq)]
+    #[destruct(parsable, emit)]
+    struct Pair {
+        data: (u8, Checksum<Crc32>),
+    }
+
+    #[derive(Destruct, Debug, PartialEq, Eq)]
+    #[destruct(parsable, emit)]
+    struct Log {
+        count: u8,
+        entries: Exactly<2, (u8, Checksum<Adler32>)>,
+    }
+
+    #[derive(Destruct, Debug, PartialEq, Eq)]
+    #[destruct(parsable, emit)]
+    struct Signed {
+        data: u8,
+        crc: Option<Checksum<Crc32>>,
+    }
+
+    #[test]
+    fn test_checksum_nested() {
+        let pair = Pair {
+            data: (7, Checksum::new(0)),
+        };
+        let mut expected = vec![7];
+        expected.extend_from_slice(&Crc32::compute(&[7]).to_be_bytes());
+        assert_eq!(to_bytes(&pair).unwrap(), expected);
+        assert_eq!(roundtrip(&pair).unwrap().data.1.value, Crc32::compute(&[7]));
+
+        // Each entry covers the bytes of `Log` before it, the entries before included.
+        let entry = |tag| (tag, Checksum::new(0));
+        let log = Log {
+            count: 2,
+            entries: Exactly(vec![entry(1), entry(2)]),
+        };
+        let bytes = to_bytes(&log).unwrap();
+        assert_eq!(bytes[2..6], Adler32::compute(&[2, 1]).to_be_bytes());
+        assert_eq!(bytes[7..11], Adler32::compute(&bytes[..7]).to_be_bytes());
+        let parsed = roundtrip(&log).unwrap();
+        assert_eq!(to_bytes(&parsed).unwrap(), bytes);
+
+        // Through `BufferedSource`, which keeps only the bytes a mark holds.
+        let signed = Signed {
+            data: 5,
+            crc: Some(Checksum::new(Crc32::compute(&[5]))),
+        };
+        let bytes = to_bytes(&signed).unwrap();
+        assert_eq!(Signed::parse(&mut &bytes[..]).unwrap(), signed);
+        assert_eq!(Signed::parse(&mut &bytes[..1]).unwrap().crc, None);
+    }
+
+    #[test]
+    fn test_checksum_outside_struct() {
+        let error = Checksum::<Crc32>::parse_slice(&mut &[0, 0, 0, 0][..]).unwrap_err();
+        let error = error.downcast::<ParseError>().unwrap();
+        assert!(matches!(
+            error.cause().downcast_ref::<NoStructBytes>(),
+            Some(NoStructBytes::NoStruct)
+        ));
+        let error = to_bytes(&Checksum::<Crc32>::new(0)).unwrap_err();
+        assert_eq!(error.to_string(), "not in a struct");
+    }
+
+    #[test]
+    fn test_checksum_mismatch() {
+        let error = Entry::parse_slice(&mut &b"123456780\xcb\xf4\x39\x26"[..]).unwrap_err();
+        let error = error.downcast::<ParseError>().unwrap();
+        assert_eq!(error.path(), "Entry.crc");
+        assert_eq!(error.offset(), 9);
+        let mismatch = error.cause().downcast_ref::<ChecksumMismatch>().unwrap();
+        assert_eq!(mismatch.stored, 0xcbf4_3926);
+        assert_eq!(
+            error.to_string(),
+            format!(
+                "Entry.crc at offset 9: CRC-32 checksum 0xcbf43926 does not match {:#010x} computed",
+                Crc32::compute(b"123456780")
+            )
+        );
+    }
+}
@@ -0,0 +1,346 @@
+//! Collections whose size is known before parsing them.
+//!
+//! ```ignore
+//! #[derive(Destruct)]
+//! #[destruct(parsable, emit)]
+//! struct Table {
+//!     rows: LengthPrefixed<u16, Vec<Row>>,
+//!     name: LengthPrefixed<u8, Vec<u8>, Bytes>,
+//!     checksum: [u8; 4],
+//!     flags: Exactly<3, u8>,
+//! }
+//! ```
+
+use crate::{
+    emit_buffered, Emit, Endian, Input, LengthMismatch, Output, Parsable, ParseError, Redaction,
+    Source, TrailingBytes,
+};
+use failure::{Error, Fail};
+use std::convert::TryFrom;
+use std::io;
+use std::marker::PhantomData;
+
+/// A number used as the length prefix of a `LengthPrefixed`.
+pub trait Length: Sized {
+    fn to_usize(&self) -> usize;
+    fn from_usize(len: usize) -> Option<Self>;
+}
+
+macro_rules! length {
+    ($($t:ty),*) => {
+        $(
+            impl Length for $t {
+                fn to_usize(&self) -> usize {
+                    usize::try_from(*self).unwrap_or(usize::MAX)
+                }
+
+                fn from_usize(len: usize) -> Option<Self> {
+                    <$t>::try_from(len).ok()
+                }
+            }
+        )*
+    };
+}
+
+length!(u8, u16, u32, u64);
+
+impl<L: Length> Length for crate::BigEndian<L> {
+    fn to_usize(&self) -> usize {
+        self.0.to_usize()
+    }
+
+    fn from_usize(len: usize) -> Option<Self> {
+        L::from_usize(len).map(crate::BigEndian)
+    }
+}
+
+impl<L: Length> Length for crate::LittleEndian<L> {
+    fn to_usize(&self) -> usize {
+        self.0.to_usize()
+    }
+
+    fn from_usize(len: usize) -> Option<Self> {
+        L::from_usize(len).map(crate::LittleEndian)
+    }
+}
+
+/// The prefix of a `LengthPrefixed` counts elements.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Count;
+
+/// The prefix of a `LengthPrefixed` counts bytes.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Bytes;
+
+/// A `T` preceded by its length as an `L`.
+///
+/// With `Count`, the default, `T` is a `Vec` and the length is its number of elements. With
+/// `Bytes`, `T` is anything parsable and the length is its size in bytes; `T` must use exactly
+/// that many.
+#[derive(new, Debug, Default, Clone, PartialEq, Eq)]
+pub struct LengthPrefixed<L, T, U = Count> {
+    pub value: T,
+    #[new(default)]
+    prefix: PhantomData<(L, U)>,
+}
+
+/// Exactly `N` elements.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Exactly<const N: usize, T>(pub Vec<T>);
+
+#[derive(Debug, Fail)]
+#[fail(display = "length {} does not fit in the length prefix", _0)]
+pub struct LengthOverflow(pub usize);
+
+/// Parse `len` elements, failing at the path of the element that does not parse.
+pub(crate) fn parse_elements<'a, T: Parsable<'a>, S: Source<'a>>(
+    input: &mut Input<S>,
+    len: usize,
+) -> Result<Vec<T>, ParseError> {
+    let mut result = Vec::new();
+    for index in 0..len {
+        result.push(T::parse_input(input).map_err(|e| e.in_index(index))?);
+    }
+    Ok(result)
+}
+
+/// Parse with `f` from the next `len` bytes, which it must use up.
+pub(crate) fn parse_limited<'a, T, S: Source<'a>>(
+    input: &mut Input<S>,
+    len: usize,
+    f: impl FnOnce(&mut Input<S>) -> Result<T, ParseError>,
+) -> Result<T, ParseError> {
+    let end = input.offset() + len;
+    let value = input.limit(len, f)?;
+    if input.offset() < end {
+        return Err(input.error(TrailingBytes(end - input.offset())));
+    }
+    Ok(value)
+}
+
+fn emit_elements<T: Emit>(
+    elements: &[T],
+    bytes: &mut Output,
+    start: Option<usize>,
+    order: Endian,
+    redaction: Redaction,
+) -> Result<(), Error> {
+    for element in elements {
+        element.emit_in_struct(bytes, start, order, redaction)?;
+    }
+    Ok(())
+}
+
+fn emit_length<L: Length + Emit, W: io::Write>(
+    len: usize,
+    write: &mut W,
+    order: Endian,
+) -> Result<(), Error> {
+    L::from_usize(len)
+        .ok_or(LengthOverflow(len))?
+        .emit_ordered(write, order)
+}
+
+impl<'a, L: Parsable<'a> + Length, T: Parsable<'a>> Parsable<'a>
+    for LengthPrefixed<L, Vec<T>, Count>
+{
+    const NEEDS_STRUCT_BYTES: bool = T::NEEDS_STRUCT_BYTES;
+
+    fn parse_input<S: Source<'a>>(input: &mut Input<S>) -> Result<Self, ParseError> {
+        let len = L::parse_input(input)?.to_usize();
+        parse_elements(input, len).map(LengthPrefixed::new)
+    }
+}
+
+impl<'a, L: Parsable<'a> + Length, T: Parsable<'a>> Parsable<'a> for LengthPrefixed<L, T, Bytes> {
+    const NEEDS_STRUCT_BYTES: bool = T::NEEDS_STRUCT_BYTES;
+
+    fn parse_input<S: Source<'a>>(input: &mut Input<S>) -> Result<Self, ParseError> {
+        let len = L::parse_input(input)?.to_usize();
+        parse_limited(input, len, T::parse_input).map(LengthPrefixed::new)
+    }
+}
+
+impl<L: Length + Emit, T: Emit> Emit for LengthPrefixed<L, Vec<T>, Count> {
+    fn emit<W: io::Write>(&self, write: &mut W) -> Result<(), Error> {
+        self.emit_ordered(write, Endian::default())
+    }
+
+    fn emit_ordered<W: io::Write>(&self, write: &mut W, order: Endian) -> Result<(), Error> {
+        emit_buffered(self, write, order)
+    }
+
+    fn emit_in_struct(
+        &self,
+        bytes: &mut Output,
+        start: Option<usize>,
+        order: Endian,
+        redaction: Redaction,
+    ) -> Result<(), Error> {
+        emit_length::<L, _>(self.value.len(), bytes, order)?;
+        emit_elements(&self.value, bytes, start, order, redaction)
+    }
+}
+
+impl<L: Length + Emit, T: Emit> Emit for LengthPrefixed<L, T, Bytes> {
+    fn emit<W: io::Write>(&self, write: &mut W) -> Result<(), Error> {
+        self.emit_ordered(write, Endian::default())
+    }
+
+    fn emit_ordered<W: io::Write>(&self, write: &mut W, order: Endian) -> Result<(), Error> {
+        emit_buffered(self, write, order)
+    }
+
+    /// Emit `value` once to learn its length, then again after the length, as checksums and
+    /// alignments in it depend on the bytes before them.
+    fn emit_in_struct(
+        &self,
+        bytes: &mut Output,
+        start: Option<usize>,
+        order: Endian,
+        redaction: Redaction,
+    ) -> Result<(), Error> {
+        let prefix = bytes.mark();
+        emit_length::<L, _>(0, bytes, order)?;
+        let value_start = bytes.len();
+        self.value.emit_in_struct(bytes, start, order, redaction)?;
+        let len = bytes.len() - value_start;
+        bytes.rewind(prefix);
+        emit_length::<L, _>(len, bytes, order)?;
+        let value_start = bytes.len();
+        self.value.emit_in_struct(bytes, start, order, redaction)?;
+        let actual = bytes.len() - value_start;
+        if actual != len {
+            return Err(LengthMismatch {
+                expected: len,
+                actual,
+            }
+            .into());
+        }
+        Ok(())
+    }
+}
+
+impl<'a, const N: usize, T: Parsable<'a>> Parsable<'a> for Exactly<N, T> {
+    const NEEDS_STRUCT_BYTES: bool = T::NEEDS_STRUCT_BYTES;
+
+    fn parse_input<S: Source<'a>>(input: &mut Input<S>) -> Result<Self, ParseError> {
+        parse_elements(input, N).map(Exactly)
+    }
+}
+
+impl<const N: usize, T: Emit> Emit for Exactly<N, T> {
+    fn emit<W: io::Write>(&self, write: &mut W) -> Result<(), Error> {
+        self.emit_ordered(write, Endian::default())
+    }
+
+    fn emit_ordered<W: io::Write>(&self, write: &mut W, order: Endian) -> Result<(), Error> {
+        emit_buffered(self, write, order)
+    }
+
+    fn emit_in_struct(
+        &self,
+        bytes: &mut Output,
+        start: Option<usize>,
+        order: Endian,
+        redaction: Redaction,
+    ) -> Result<(), Error> {
+        if self.0.len() != N {
+            return Err(LengthMismatch {
+                expected: N,
+                actual: self.0.len(),
+            }
+            .into());
+        }
+        emit_elements(&self.0, bytes, start, order, redaction)
+    }
+}
+
+impl<'a, const N: usize, T: Parsable<'a>> Parsable<'a> for [T; N] {
+    const NEEDS_STRUCT_BYTES: bool = T::NEEDS_STRUCT_BYTES;
+
+    fn parse_input<S: Source<'a>>(input: &mut Input<S>) -> Result<Self, ParseError> {
+        let elements = parse_elements(input, N)?;
+        Ok(<[T; N]>::try_from(elements).unwrap_or_else(|_| unreachable!()))
+    }
+}
+
+impl<const N: usize, T: Emit> Emit for [T; N] {
+    fn emit<W: io::Write>(&self, write: &mut W) -> Result<(), Error> {
+        self.emit_ordered(write, Endian::default())
+    }
+
+    fn emit_ordered<W: io::Write>(&self, write: &mut W, order: Endian) -> Result<(), Error> {
+        emit_buffered(self, write, order)
+    }
+
+    fn emit_in_struct(
+        &self,
+        bytes: &mut Output,
+        start: Option<usize>,
+        order: Endian,
+        redaction: Redaction,
+    ) -> Result<(), Error> {
+        emit_elements(self, bytes, start, order, redaction)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    #[derive(Destruct, Debug, PartialEq, Eq)]
+    #[destruct(parsable, emit)]
+    struct Point {
+        x: u8,
+        y: u8,
+    }
+
+    #[derive(Destruct, Debug, PartialEq, Eq)]
+    #[destruct(parsable, emit)]
+    struct Table {
+        points: LengthPrefixed<BigEndian<u16>, Vec<Point>>,
+        name: LengthPrefixed<u8, Vec<Validated<u8, IsAsciiLowerCase>>, Bytes>,
+        magic: [u8; 2],
+        flags: Exactly<3, u8>,
+    }
+
+    fn table() -> Table {
+        Table {
+            points: LengthPrefixed::new(vec![Point { x: 1, y: 2 }, Point { x: 3, y: 4 }]),
+            name: LengthPrefixed::new(vec![Validated::new(b'a'), Validated::new(b'b')]),
+            magic: [0xca, 0xfe],
+            flags: Exactly(vec![1, 0, 1]),
+        }
+    }
+
+    #[test]
+    fn test_collections() {
+        let bytes = [0, 2, 1, 2, 3, 4, 2, b'a', b'b', 0xca, 0xfe, 1, 0, 1];
+        assert_eq!(to_bytes(&table()).unwrap(), bytes);
+        assert_eq!(Table::parse_slice(&mut &bytes[..]).unwrap(), table());
+        assert_eq!(roundtrip(&table()).unwrap(), table());
+    }
+
+    #[test]
+    fn test_collection_errors() {
+        // The third point is cut short.
+        let error = Table::parse_slice(&mut &[0, 3, 1, 2, 3, 4, 5][..]).unwrap_err();
+        let error = error.downcast::<ParseError>().unwrap();
+        assert_eq!(error.path(), "Table.points[2].y");
+
+        // The name holds a byte the validator rejects, so it does not use its whole length.
+        let bytes = [0, 0, 3, b'a', b'b', b'1', 0xca, 0xfe, 1, 0, 1];
+        let error = Table::parse_slice(&mut &bytes[..]).unwrap_err();
+        let error = error.downcast::<ParseError>().unwrap();
+        assert_eq!(error.path(), "Table.name");
+        assert_eq!(error.offset(), 5);
+
+        let mut short = table();
+        short.flags = Exactly(vec![1]);
+        assert!(to_bytes(&short).is_err());
+
+        let long = LengthPrefixed::<u8, Vec<u8>>::new(vec![0; 256]);
+        assert!(to_bytes(&long).is_err());
+    }
+}
@@ -0,0 +1,250 @@
+//! Fields whose parsing depends on numbers parsed before them.
+//!
+//! ```ignore
+//! #[derive(Destruct)]
+//! #[destruct(parsable)]
+//! struct Header {
+//!     kind: u8,
+//!     count: u8,
+//!     len: u16,
+//! }
+//!
+//! #[derive(Destruct)]
+//! #[destruct(parsable)]
+//! struct Packet {
+//!     header: Header,
+//!     #[destruct(meta(count = "header.count"))]
+//!     entries: Vec<u32>,
+//!     #[destruct(meta(len = "header.len"))]
+//!     payload: Payload,
+//! }
+//! ```
+//!
+//! `len` is the size of the field in bytes, which it must use up. `count` is the number of
+//! elements of a `Vec` field. Both name a number parsed earlier in the same struct, possibly
+//! nested in a struct field, like `header.len`, or `0` for the first field of a tuple struct.
+//! Numbers are the integers, and types delegating `Parsable::number` to one.
+
+use crate::collection::parse_limited;
+use crate::{Input, NoStructBytes, Parsable, ParseError, Source};
+use destruct::DestructFieldMetadata;
+use failure::Fail;
+use std::borrow::Cow;
+use std::convert::TryFrom;
+
+/// What the `Input` keeps for the `len` and `count` of dependent fields, and for checksums.
+#[derive(Debug, Default)]
+pub(crate) struct Dependencies {
+    /// The numbers parsed in each struct being parsed, innermost last.
+    scopes: Vec<Scope>,
+    /// The numbers of the struct parsed last, to be added to the scope of its field.
+    finished: Option<Scope>,
+    /// The `count` of the field being parsed, until its `Vec` takes it.
+    count: Option<usize>,
+}
+
+impl Dependencies {
+    pub(crate) fn count(&self) -> Option<usize> {
+        self.count
+    }
+
+    /// Offset of the struct being parsed.
+    pub(crate) fn struct_start(&self) -> Option<usize> {
+        self.scopes.last().map(|scope| scope.start)
+    }
+
+    /// Offset of the struct being parsed, which must keep its bytes for `Input::struct_bytes`.
+    pub(crate) fn kept_struct_start(&self) -> Result<usize, NoStructBytes> {
+        match self.scopes.last() {
+            Some(scope) if scope.keeps_bytes => Ok(scope.start),
+            Some(_) => Err(NoStructBytes::NotKept),
+            None => Err(NoStructBytes::NoStruct),
+        }
+    }
+}
+
+/// Numbers by path from the struct they were parsed in.
+#[derive(Debug, Default)]
+struct Scope {
+    start: usize,
+    /// Whether a mark holds the bytes from `start`, see `Parsable::NEEDS_STRUCT_BYTES`.
+    keeps_bytes: bool,
+    numbers: Vec<(Cow<'static, str>, i128)>,
+}
+
+impl Scope {
+    fn get(&self, path: &str) -> Option<i128> {
+        self.numbers
+            .iter()
+            .rev()
+            .find(|(name, _)| name == path)
+            .map(|(_, number)| *number)
+    }
+}
+
+#[derive(Debug, Fail)]
+#[fail(display = "no number parsed at {} before this field", _0)]
+pub struct UnknownPath(pub &'static str);
+
+#[derive(Debug, Fail)]
+#[fail(display = "{} = {} is not a valid {}", path, number, key)]
+pub struct InvalidDependency {
+    pub key: &'static str,
+    pub path: &'static str,
+    pub number: i128,
+}
+
+#[derive(Debug, Fail)]
+#[fail(display = "count applies to a Vec field")]
+pub struct CountUnused;
+
+impl<'a, S: Source<'a>> Input<S> {
+    /// Number of elements the next `Vec` must have, set by the `count` of its field.
+    pub fn take_count(&mut self) -> Option<usize> {
+        self.dependencies().count.take()
+    }
+
+    /// Run `f` parsing a struct, whose fields may refer to the numbers parsed before them.
+    /// `keeps_bytes` if the struct holds a mark at its start.
+    pub(crate) fn in_scope<T>(
+        &mut self,
+        keeps_bytes: bool,
+        f: impl FnOnce(&mut Self) -> Result<T, ParseError>,
+    ) -> Result<T, ParseError> {
+        let scope = Scope {
+            start: self.offset(),
+            keeps_bytes,
+            ..Scope::default()
+        };
+        self.dependencies().scopes.push(scope);
+        let result = f(self);
+        let scope = self.dependencies().scopes.pop();
+        if result.is_ok() {
+            self.dependencies().finished = scope;
+        }
+        result
+    }
+
+    /// The `key` of the field with metadata `M`, from the number its path refers to.
+    fn dependency<M: DestructFieldMetadata>(
+        &mut self,
+        key: &'static str,
+    ) -> Result<Option<usize>, ParseError> {
+        let path = match M::meta().iter().find(|(k, _)| *k == key) {
+            Some((_, path)) => *path,
+            None => return Ok(None),
+        };
+        let number = self
+            .dependencies()
+            .scopes
+            .last()
+            .and_then(|scope| scope.get(path))
+            .ok_or_else(|| self.error(UnknownPath(path)))?;
+        let invalid = InvalidDependency { key, path, number };
+        usize::try_from(number)
+            .map(Some)
+            .map_err(|_| self.error(invalid))
+    }
+
+    /// Remember `number`, the value of the field with metadata `M` just parsed, and the numbers
+    /// of the struct it holds if any.
+    fn record<M: DestructFieldMetadata>(&mut self, number: Option<i128>) {
+        let finished = self.dependencies().finished.take();
+        let scope = match self.dependencies().scopes.last_mut() {
+            Some(scope) => scope,
+            None => return,
+        };
+        let name = if M::named_fields() {
+            Cow::Borrowed(M::field_name())
+        } else {
+            Cow::Owned(M::field_index().to_string())
+        };
+        if let Some(finished) = finished {
+            for (path, number) in finished.numbers {
+                scope
+                    .numbers
+                    .push((Cow::Owned(format!("{}.{}", name, path)), number));
+            }
+        }
+        if let Some(number) = number {
+            scope.numbers.push((name, number));
+        }
+    }
+}
+
+/// Parse the field with metadata `M`, applying its `len` and `count`.
+pub(crate) fn parse_field<'a, H: Parsable<'a>, M: DestructFieldMetadata, S: Source<'a>>(
+    input: &mut Input<S>,
+) -> Result<H, ParseError> {
+    let len = input.dependency::<M>("len")?;
+    let count = input.dependency::<M>("count")?;
+    input.dependencies().finished = None;
+    input.dependencies().count = count;
+    let head = match len {
+        Some(len) => parse_limited(input, len, H::parse_input),
+        None => H::parse_input(input),
+    };
+    if input.take_count().is_some() && head.is_ok() {
+        return Err(input.error(CountUnused));
+    }
+    let head = head?;
+    input.record::<M>(head.number());
+    Ok(head)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    #[derive(Destruct, Debug, PartialEq, Eq)]
+    #[destruct(parsable, emit)]
+    struct Header {
+        count: u8,
+        len: BigEndian<u16>,
+    }
+
+    #[derive(Destruct, Debug, PartialEq, Eq)]
+    #[destruct(parsable, emit)]
+    struct Packet {
+        header: Header,
+        #[destruct(meta(count = "header.count"))]
+        entries: Vec<u16>,
+        #[destruct(meta(len = "header.len"))]
+        payload: Vec<u8>,
+        trailer: u8,
+    }
+
+    #[derive(Destruct, Debug, PartialEq, Eq)]
+    #[destruct(parsable)]
+    struct Chunk(u8, #[destruct(meta(len = "0"))] Validated<u8, IsAsciiDigit>);
+
+    #[test]
+    fn test_dependent() {
+        let bytes = [2, 0, 3, 0, 1, 0, 2, 7, 8, 9, 0xff];
+        let packet = Packet::parse_slice(&mut &bytes[..]).unwrap();
+        assert_eq!(packet.entries, [1, 2]);
+        assert_eq!(packet.payload, [7, 8, 9]);
+        assert_eq!(packet.trailer, 0xff);
+        assert_eq!(to_bytes(&packet).unwrap(), bytes);
+    }
+
+    #[test]
+    fn test_dependent_errors() {
+        let error = Packet::parse_slice(&mut &[3, 0, 0, 0, 1, 0, 2][..]).unwrap_err();
+        let error = error.downcast::<ParseError>().unwrap();
+        assert_eq!(error.path(), "Packet.entries[2]");
+
+        let error = Packet::parse_slice(&mut &[0, 0, 4, 1, 2][..]).unwrap_err();
+        let error = error.downcast::<ParseError>().unwrap();
+        assert_eq!(error.path(), "Packet.payload");
+
+        let error = Chunk::parse_slice(&mut &[2, b'1', b'2'][..]).unwrap_err();
+        let error = error.downcast::<ParseError>().unwrap();
+        assert_eq!(error.path(), "Chunk.1");
+        assert_eq!(
+            error.to_string(),
+            "Chunk.1 at offset 2: 1 bytes left after parsing"
+        );
+        assert!(Chunk::parse_slice(&mut &[1, b'1'][..]).is_ok());
+    }
+}
@@ -0,0 +1,481 @@
+use crate::{
+    Endian, Input, Output, Parsable, ParseError, SliceSource, ValidateError, Validated, Validator,
+};
+use destruct::*;
+use failure::{Error, Fail};
+use std::io;
+
+pub use destruct::print::Redaction;
+
+/// The writing counterpart of `Parsable`.
+///
+/// For derived types `T::parse` reads back what `emit` wrote, as long as the format is
+/// unambiguous: an enum variant must not parse the bytes of a later variant, and a `Vec<T>` must
+/// be followed by bytes that `T` does not parse, and a skipped separator such as whitespace must
+/// not be needed between fields, as `emit` writes none. `emit` does not check it, `roundtrip`
+/// and `check_emitted` do for a given value.
+///
+/// Fields marked `#[destruct(sensitive)]` are written as zeros, as many bytes as the value takes,
+/// so the bytes around them keep their place but the value need not read back. `emit_with` and
+/// `to_bytes_with` write them with `Redaction::Reveal`, which `roundtrip` uses.
+pub trait Emit {
+    fn emit<W: io::Write>(&self, write: &mut W) -> Result<(), Error>;
+
+    /// Emit with the byte order of the enclosing struct, the counterpart of `Input::order`.
+    fn emit_ordered<W: io::Write>(&self, write: &mut W, _order: Endian) -> Result<(), Error> {
+        self.emit(write)
+    }
+
+    /// Emit after `bytes`, what was emitted so far from the value passed to `emit`, which starts
+    /// the stream. The enclosing struct starts at `start` in `bytes`, `None` outside of a struct.
+    ///
+    /// Types holding other values emit them with this, so that checksums, alignments and bit
+    /// fields inside see the bytes before them, and emit on their own with `emit_buffered`. They
+    /// pass `redaction` on to the fields, which mask sensitive values unless it is `Reveal`.
+    fn emit_in_struct(
+        &self,
+        bytes: &mut Output,
+        _start: Option<usize>,
+        order: Endian,
+        _redaction: Redaction,
+    ) -> Result<(), Error> {
+        self.emit_ordered(bytes, order)
+    }
+}
+
+#[derive(Debug, Fail)]
+#[fail(display = "{} bytes left after parsing", _0)]
+pub struct TrailingBytes(pub usize);
+
+/// `Emit::emit_ordered` of a type holding other values: emit `value` with `Emit::emit_in_struct`
+/// into a buffer starting the stream, then to `write`. Sensitive fields are masked.
+pub fn emit_buffered<T: Emit + ?Sized, W: io::Write>(
+    value: &T,
+    write: &mut W,
+    order: Endian,
+) -> Result<(), Error> {
+    let mut bytes = Output::new();
+    value.emit_in_struct(&mut bytes, None, order, Redaction::Redact)?;
+    write.write_all(&bytes)?;
+    Ok(())
+}
+
+/// Emit `value` into a new buffer, with sensitive fields masked.
+pub fn to_bytes<T: Emit + ?Sized>(value: &T) -> Result<Vec<u8>, Error> {
+    let mut result = Vec::new();
+    value.emit(&mut result)?;
+    Ok(result)
+}
+
+/// Emit `value` to `write`, writing sensitive fields as `redaction` says.
+pub fn emit_with<T: Emit + ?Sized, W: io::Write>(
+    value: &T,
+    write: &mut W,
+    redaction: Redaction,
+) -> Result<(), Error> {
+    let mut bytes = Output::new();
+    value.emit_in_struct(&mut bytes, None, Endian::default(), redaction)?;
+    write.write_all(&bytes)?;
+    Ok(())
+}
+
+pub fn to_bytes_with<T: Emit + ?Sized>(value: &T, redaction: Redaction) -> Result<Vec<u8>, Error> {
+    let mut result = Vec::new();
+    emit_with(value, &mut result, redaction)?;
+    Ok(result)
+}
+
+#[derive(Debug, Fail)]
+#[fail(display = "emitted bytes read back as a different value")]
+pub struct RoundtripMismatch;
+
+/// Parse `bytes`, just emitted, back with `parse`, failing unless that reads every byte and the
+/// value read emits `bytes` again. Comparing bytes rather than values needs no `PartialEq`, and
+/// still tells apart values that an unambiguous format writes differently. The value read is
+/// emitted with `Redaction::Reveal`, masked bytes read back as the zeros they are.
+pub fn check_emitted<'a, T: Emit>(
+    bytes: &'a [u8],
+    parse: impl FnOnce(&mut Input<SliceSource<'a>>) -> Result<T, ParseError>,
+) -> Result<(), Error> {
+    let mut input = Input::new(SliceSource::new(bytes));
+    let value = parse(&mut input)?;
+    let rest = input.source().remaining();
+    if !rest.is_empty() {
+        return Err(TrailingBytes(rest.len()).into());
+    }
+    let mut again = Output::new();
+    value.emit_in_struct(&mut again, None, Endian::default(), Redaction::Reveal)?;
+    if *again == *bytes {
+        Ok(())
+    } else {
+        Err(RoundtripMismatch.into())
+    }
+}
+
+/// Emit `value`, sensitive fields included, and parse it back, failing if the parse does not
+/// consume every byte.
+pub fn roundtrip<T: Emit + for<'a> Parsable<'a>>(value: &T) -> Result<T, Error> {
+    let bytes = to_bytes_with(value, Redaction::Reveal)?;
+    let mut input = Input::new(SliceSource::new(&bytes));
+    let result = T::parse_input(&mut input)?;
+    let rest = input.source().remaining();
+    if rest.is_empty() {
+        Ok(result)
+    } else {
+        Err(TrailingBytes(rest.len()).into())
+    }
+}
+
+impl<T: Emit + ?Sized> Emit for &T {
+    fn emit<W: io::Write>(&self, write: &mut W) -> Result<(), Error> {
+        (**self).emit(write)
+    }
+
+    fn emit_ordered<W: io::Write>(&self, write: &mut W, order: Endian) -> Result<(), Error> {
+        (**self).emit_ordered(write, order)
+    }
+
+    fn emit_in_struct(
+        &self,
+        bytes: &mut Output,
+        start: Option<usize>,
+        order: Endian,
+        redaction: Redaction,
+    ) -> Result<(), Error> {
+        (**self).emit_in_struct(bytes, start, order, redaction)
+    }
+}
+
+impl Emit for u8 {
+    fn emit<W: io::Write>(&self, write: &mut W) -> Result<(), Error> {
+        write.write_all(&[*self])?;
+        Ok(())
+    }
+}
+
+impl<M: DestructMetadata + 'static> Emit for DestructEnd<M> {
+    fn emit<W: io::Write>(&self, _: &mut W) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+impl<H: Emit, T: Emit, M: DestructFieldMetadata + 'static> Emit for DestructField<H, T, M> {
+    fn emit<W: io::Write>(&self, write: &mut W) -> Result<(), Error> {
+        self.emit_ordered(write, Endian::default())
+    }
+
+    fn emit_ordered<W: io::Write>(&self, write: &mut W, order: Endian) -> Result<(), Error> {
+        emit_buffered(self, write, order)
+    }
+
+    fn emit_in_struct(
+        &self,
+        bytes: &mut Output,
+        start: Option<usize>,
+        order: Endian,
+        redaction: Redaction,
+    ) -> Result<(), Error> {
+        let field = bytes.mark();
+        self.head.emit_in_struct(bytes, start, order, redaction)?;
+        if M::sensitive() && redaction == Redaction::Redact {
+            bytes.mask(field);
+        }
+        self.tail.emit_in_struct(bytes, start, order, redaction)
+    }
+}
+
+impl<F: Emit, M: DestructMetadata + 'static> Emit for DestructBegin<F, M> {
+    fn emit<W: io::Write>(&self, write: &mut W) -> Result<(), Error> {
+        self.emit_ordered(write, Endian::default())
+    }
+
+    fn emit_ordered<W: io::Write>(&self, write: &mut W, order: Endian) -> Result<(), Error> {
+        emit_buffered(self, write, order)
+    }
+
+    fn emit_in_struct(
+        &self,
+        bytes: &mut Output,
+        _: Option<usize>,
+        order: Endian,
+        redaction: Redaction,
+    ) -> Result<(), Error> {
+        let start = bytes.len();
+        self.fields
+            .emit_in_struct(bytes, Some(start), order, redaction)?;
+        bytes.check_aligned()
+    }
+}
+
+impl<M: DestructEnumMetadata + 'static> Emit for DestructEnumEnd<M> {
+    fn emit<W: io::Write>(&self, _: &mut W) -> Result<(), Error> {
+        unreachable!("enum {} has no variants", M::enum_name())
+    }
+}
+
+impl<H: Emit, T: Emit, M: DestructEnumVariantMetadata + 'static> Emit
+    for DestructEnumVariant<H, T, M>
+{
+    fn emit<W: io::Write>(&self, write: &mut W) -> Result<(), Error> {
+        self.emit_ordered(write, Endian::default())
+    }
+
+    fn emit_ordered<W: io::Write>(&self, write: &mut W, order: Endian) -> Result<(), Error> {
+        emit_buffered(self, write, order)
+    }
+
+    fn emit_in_struct(
+        &self,
+        bytes: &mut Output,
+        start: Option<usize>,
+        order: Endian,
+        redaction: Redaction,
+    ) -> Result<(), Error> {
+        match self {
+            DestructEnumVariant::Head(head, _) => {
+                head.emit_in_struct(bytes, start, order, redaction)
+            }
+            DestructEnumVariant::Tail(tail, _) => {
+                tail.emit_in_struct(bytes, start, order, redaction)
+            }
+        }
+    }
+}
+
+impl<T: Emit, M: DestructEnumMetadata + 'static> Emit for DestructEnumBegin<T, M> {
+    fn emit<W: io::Write>(&self, write: &mut W) -> Result<(), Error> {
+        self.emit_ordered(write, Endian::default())
+    }
+
+    fn emit_ordered<W: io::Write>(&self, write: &mut W, order: Endian) -> Result<(), Error> {
+        emit_buffered(self, write, order)
+    }
+
+    fn emit_in_struct(
+        &self,
+        bytes: &mut Output,
+        start: Option<usize>,
+        order: Endian,
+        redaction: Redaction,
+    ) -> Result<(), Error> {
+        self.variants.emit_in_struct(bytes, start, order, redaction)
+    }
+}
+
+impl<T: Emit, F: Validator<T>> Emit for Validated<T, F> {
+    fn emit<W: io::Write>(&self, write: &mut W) -> Result<(), Error> {
+        self.emit_ordered(write, Endian::default())
+    }
+
+    fn emit_ordered<W: io::Write>(&self, write: &mut W, order: Endian) -> Result<(), Error> {
+        emit_buffered(self, write, order)
+    }
+
+    /// Validate again, a `Validated` built with `new` may hold anything.
+    fn emit_in_struct(
+        &self,
+        bytes: &mut Output,
+        start: Option<usize>,
+        order: Endian,
+        redaction: Redaction,
+    ) -> Result<(), Error> {
+        if F::validate(&self.value) {
+            self.value.emit_in_struct(bytes, start, order, redaction)
+        } else {
+            Err(ValidateError(F::description()).into())
+        }
+    }
+}
+
+impl<T: Emit> Emit for Vec<T> {
+    fn emit<W: io::Write>(&self, write: &mut W) -> Result<(), Error> {
+        self.emit_ordered(write, Endian::default())
+    }
+
+    fn emit_ordered<W: io::Write>(&self, write: &mut W, order: Endian) -> Result<(), Error> {
+        emit_buffered(self, write, order)
+    }
+
+    fn emit_in_struct(
+        &self,
+        bytes: &mut Output,
+        start: Option<usize>,
+        order: Endian,
+        redaction: Redaction,
+    ) -> Result<(), Error> {
+        for item in self {
+            item.emit_in_struct(bytes, start, order, redaction)?;
+        }
+        Ok(())
+    }
+}
+
+/// Use macros to workaround overlapping impls
+///
+/// Takes the same options as `parsable!`, which must match for `roundtrip` to hold.
+/// `#[destruct(parsable(...), emit)]` passes the options of `parsable`.
+#[macro_export]
+macro_rules! emit {
+    ($t:ident < $lt:lifetime > $(, $($options:tt)*)?) => {
+        impl<$lt> $crate::Emit for $t<$lt> {
+            $crate::emit!(@methods $($($options)*)?);
+        }
+    };
+    ($t:ident $(, $($options:tt)*)?) => {
+        impl $crate::Emit for $t {
+            $crate::emit!(@methods $($($options)*)?);
+        }
+    };
+    (@methods $($options:tt)*) => {
+        fn emit<W: std::io::Write>(
+            &self,
+            write: &mut W,
+        ) -> Result<(), $crate::__private::failure::Error> {
+            $crate::emit_buffered(self, write, $crate::Endian::default())
+        }
+
+        $crate::emit!(@ordered $($options)*);
+    };
+    (@ordered tag($tag:ty) $(, $($options:tt)*)?) => {
+        fn emit_ordered<W: std::io::Write>(
+            &self,
+            write: &mut W,
+            order: $crate::Endian,
+        ) -> Result<(), $crate::__private::failure::Error> {
+            $crate::emit_buffered(self, write, order)
+        }
+
+        fn emit_in_struct(
+            &self,
+            bytes: &mut $crate::Output,
+            start: Option<usize>,
+            order: $crate::Endian,
+            redaction: $crate::Redaction,
+        ) -> Result<(), $crate::__private::failure::Error> {
+            $crate::parser_options!($($($options)*)?).emit_in_struct(
+                &$crate::Tagged::<$tag, _>::new(
+                    $crate::__private::destruct::DestructRef::destruct_ref(self),
+                ),
+                bytes,
+                start,
+                order,
+                redaction,
+            )
+        }
+    };
+    (@ordered $($options:tt)*) => {
+        fn emit_ordered<W: std::io::Write>(
+            &self,
+            write: &mut W,
+            order: $crate::Endian,
+        ) -> Result<(), $crate::__private::failure::Error> {
+            $crate::emit_buffered(self, write, order)
+        }
+
+        fn emit_in_struct(
+            &self,
+            bytes: &mut $crate::Output,
+            start: Option<usize>,
+            order: $crate::Endian,
+            redaction: $crate::Redaction,
+        ) -> Result<(), $crate::__private::failure::Error> {
+            $crate::parser_options!($($options)*).emit_in_struct(
+                &$crate::__private::destruct::DestructRef::destruct_ref(self),
+                bytes,
+                start,
+                order,
+                redaction,
+            )
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parsable, BigEndian, IsAsciiDigit, IsAsciiLowerCase};
+
+    #[derive(Debug, Destruct, PartialEq)]
+    #[destruct(parsable(little_endian), emit)]
+    struct Record {
+        id: u32,
+        port: BigEndian<u16>,
+        kind: Kind,
+        name: Vec<Validated<u8, IsAsciiLowerCase>>,
+    }
+
+    #[derive(Debug, Destruct, PartialEq)]
+    #[destruct(parsable, emit)]
+    enum Kind {
+        Digit(Validated<u8, IsAsciiDigit>),
+        Pair(u8, u16),
+    }
+
+    #[test]
+    fn test_emit() {
+        let record = Record {
+            id: 1,
+            port: BigEndian(80),
+            kind: Kind::Pair(b'x', 2),
+            name: vec![Validated::new(b'a'), Validated::new(b'b')],
+        };
+        assert_eq!(
+            to_bytes(&record).unwrap(),
+            vec![1, 0, 0, 0, 0, 80, b'x', 2, 0, b'a', b'b']
+        );
+        assert_eq!(roundtrip(&record).unwrap(), record);
+    }
+
+    #[derive(Debug, Destruct, PartialEq)]
+    #[destruct(parsable, emit)]
+    struct Login {
+        user: u8,
+        #[destruct(sensitive)]
+        pin: BigEndian<u16>,
+    }
+
+    #[test]
+    fn test_emit_sensitive() {
+        let login = Login {
+            user: 7,
+            pin: BigEndian(1234),
+        };
+        assert!(login.destruct_ref().fields.tail.sensitive());
+        assert_eq!(to_bytes(&login).unwrap(), vec![7, 0, 0]);
+        let bytes = to_bytes_with(&login, Redaction::Reveal).unwrap();
+        assert_eq!(bytes, vec![7, 0x04, 0xd2]);
+        assert_eq!(roundtrip(&login).unwrap(), login);
+        let mut written = Vec::new();
+        login.emit_ordered(&mut written, Endian::Little).unwrap();
+        assert_eq!(written, vec![7, 0, 0]);
+        let nested = vec![login];
+        assert_eq!(to_bytes(&nested).unwrap(), vec![7, 0, 0]);
+        assert_eq!(to_bytes_with(&nested, Redaction::Reveal).unwrap(), bytes);
+    }
+
+    #[test]
+    fn test_emit_validates() {
+        let kind = Kind::Digit(Validated::new(b'z'));
+        assert!(to_bytes(&kind).is_err());
+        let kind = Kind::Digit(Validated::new(b'7'));
+        assert_eq!(to_bytes(&kind).unwrap(), vec![b'7']);
+        assert_eq!(roundtrip(&kind).unwrap(), kind);
+    }
+
+    #[test]
+    fn test_emit_ambiguous() {
+        // `Pair` starting with a digit reads back as `Digit`, leaving the rest unparsed.
+        let kind = Kind::Pair(b'1', 2);
+        let bytes = to_bytes(&kind).unwrap();
+        assert_eq!(bytes, vec![b'1', 0, 2]);
+        let error = check_emitted(&bytes, Kind::parse_input).unwrap_err();
+        assert_eq!(error.to_string(), "2 bytes left after parsing");
+        let record = Record {
+            id: 1,
+            port: BigEndian(80),
+            kind,
+            name: vec![],
+        };
+        assert!(roundtrip(&record).is_err());
+    }
+}
@@ -0,0 +1,426 @@
+use crate::bits::{BitCursor, Unaligned};
+use crate::dependent::Dependencies;
+use crate::memo::MemoTable;
+use crate::{BitOrder, Choice, Endian, Skipper, Source};
+use destruct::DestructEnumVariantMetadata;
+use failure::{Error, Fail};
+use std::fmt;
+use std::io;
+use std::sync::Arc;
+
+/// A `Source` that keeps track of the byte offset and the current byte order.
+#[derive(Debug)]
+pub struct Input<S> {
+    source: S,
+    offset: usize,
+    order: Endian,
+    bit_order: BitOrder,
+    /// Where bit fields are within the current byte, see `Input::read_bits`.
+    bits: BitCursor,
+    /// What is skipped before each field, see `Input::skip_ignored`.
+    skipper: Skipper,
+    /// Choice policy of the next enum, see `Input::take_choice`.
+    choice: Choice,
+    /// Offset the input ends at for the value being parsed, see `Input::limit`.
+    end: Option<usize>,
+    /// Results of `Memo` types, see `Input::memoize`.
+    memo: Option<MemoTable>,
+    /// Numbers parsed so far, for dependent fields.
+    dependencies: Dependencies,
+}
+
+/// An offset `Input::rewind` can go back to. Every mark is either rewound to or released.
+#[derive(Debug)]
+#[must_use]
+pub struct Mark {
+    offset: usize,
+    bits: BitCursor,
+}
+
+impl Mark {
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+}
+
+impl<'a, S: Source<'a>> Input<S> {
+    pub fn new(source: S) -> Self {
+        Input {
+            source,
+            offset: 0,
+            order: Endian::default(),
+            bit_order: BitOrder::default(),
+            bits: BitCursor::default(),
+            skipper: Skipper::default(),
+            choice: Choice::default(),
+            end: None,
+            memo: None,
+            dependencies: Dependencies::default(),
+        }
+    }
+
+    /// Remember the results of `Memo` types, up to `capacity` of them. Past that, the oldest are
+    /// forgotten.
+    pub fn memoize(mut self, capacity: usize) -> Self {
+        self.memo = Some(MemoTable::new(capacity));
+        self
+    }
+
+    /// Number of results remembered for `Memo` types.
+    pub fn memo_len(&self) -> usize {
+        self.memo.as_ref().map_or(0, MemoTable::len)
+    }
+
+    pub(crate) fn memo(&self) -> Option<&MemoTable> {
+        self.memo.as_ref()
+    }
+
+    pub(crate) fn memo_mut(&mut self) -> Option<&mut MemoTable> {
+        self.memo.as_mut()
+    }
+
+    pub(crate) fn dependencies(&mut self) -> &mut Dependencies {
+        &mut self.dependencies
+    }
+
+    /// Number of bytes consumed so far.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Byte order of multi-byte numbers, set by the innermost struct with a byte order option.
+    pub fn order(&self) -> Endian {
+        self.order
+    }
+
+    /// Run `f` with `order` as the byte order, restoring the previous one afterwards.
+    pub fn with_order<T>(&mut self, order: Endian, f: impl FnOnce(&mut Self) -> T) -> T {
+        let previous = std::mem::replace(&mut self.order, order);
+        let result = f(self);
+        self.order = previous;
+        result
+    }
+
+    /// Order of the bits of bit fields, set by the innermost struct with a bit order option.
+    pub fn bit_order(&self) -> BitOrder {
+        self.bit_order
+    }
+
+    /// Run `f` with `bit_order`, restoring the previous one afterwards.
+    pub fn with_bit_order<T>(&mut self, bit_order: BitOrder, f: impl FnOnce(&mut Self) -> T) -> T {
+        let previous = std::mem::replace(&mut self.bit_order, bit_order);
+        let result = f(self);
+        self.bit_order = previous;
+        result
+    }
+
+    pub(crate) fn bit_cursor(&self) -> BitCursor {
+        self.bits
+    }
+
+    pub(crate) fn set_bit_cursor(&mut self, bits: BitCursor) {
+        self.bits = bits;
+    }
+
+    /// What is skipped before the fields of structs, set by the innermost struct with a skipper
+    /// option.
+    pub fn skipper(&self) -> Skipper {
+        self.skipper
+    }
+
+    /// Run `f` with `skipper`, restoring the previous one afterwards.
+    pub fn with_skipper<T>(&mut self, skipper: Skipper, f: impl FnOnce(&mut Self) -> T) -> T {
+        let previous = std::mem::replace(&mut self.skipper, skipper);
+        let result = f(self);
+        self.skipper = previous;
+        result
+    }
+
+    /// Set how the next enum parsed picks among its variants.
+    pub fn set_choice(&mut self, choice: Choice) {
+        self.choice = choice;
+    }
+
+    /// The choice policy set for the enum about to be parsed, resetting it to `Choice::Ordered`
+    /// for the enums nested in it.
+    pub fn take_choice(&mut self) -> Choice {
+        std::mem::take(&mut self.choice)
+    }
+
+    /// Run `f` with the input ending `len` bytes from here, as if that was the end of the source.
+    pub fn limit<T>(&mut self, len: usize, f: impl FnOnce(&mut Self) -> T) -> T {
+        let end = self.offset + len;
+        let previous = self.end;
+        self.end = Some(previous.map_or(end, |previous| previous.min(end)));
+        let result = f(self);
+        self.end = previous;
+        result
+    }
+
+    pub(crate) fn end(&self) -> Option<usize> {
+        self.end
+    }
+
+    /// Continue at `offset`, which was read before while a mark before it was held, skipping the
+    /// bytes up to it.
+    pub(crate) fn skip_to(&mut self, offset: usize) -> Result<(), ParseError> {
+        self.source
+            .rewind(offset)
+            .map_err(|e| ParseError::new(self.offset, e))?;
+        self.offset = offset;
+        Ok(())
+    }
+
+    /// Remember the current offset, to come back to it if what follows fails to parse.
+    pub fn mark(&mut self) -> Mark {
+        self.source.mark(self.offset);
+        Mark {
+            offset: self.offset,
+            bits: self.bits,
+        }
+    }
+
+    /// Go back to `mark`.
+    pub fn rewind(&mut self, mark: Mark) -> Result<(), ParseError> {
+        self.source
+            .rewind(mark.offset)
+            .map_err(|e| ParseError::new(mark.offset, e))?;
+        self.offset = mark.offset;
+        self.bits = mark.bits;
+        self.source.release(mark.offset);
+        Ok(())
+    }
+
+    /// Keep what was parsed since `mark`.
+    pub fn release(&mut self, mark: Mark) {
+        self.source.release(mark.offset);
+    }
+
+    /// The bytes not read yet, borrowed from the input.
+    pub fn unread(&self) -> Result<&'a [u8], ParseError> {
+        let unread = self
+            .source
+            .unread()
+            .ok_or_else(|| self.error(NotBorrowable))?;
+        match self.end {
+            Some(end) => Ok(&unread[..unread.len().min(end - self.offset)]),
+            None => Ok(unread),
+        }
+    }
+
+    /// Read `len` bytes, borrowed from the input.
+    pub fn borrow(&mut self, len: usize) -> Result<&'a [u8], ParseError> {
+        self.check_aligned()?;
+        let unread = self.unread()?;
+        if unread.len() < len {
+            let eof = io::Error::new(io::ErrorKind::UnexpectedEof, "failed to fill whole buffer");
+            return Err(ParseError::new(self.offset + unread.len(), eof));
+        }
+        let bytes = self
+            .source
+            .borrow(len)
+            .ok_or_else(|| self.error(NotBorrowable))?;
+        self.offset += len;
+        Ok(bytes)
+    }
+
+    /// A `ParseError` at the current offset.
+    pub fn error(&self, cause: impl Into<Error>) -> ParseError {
+        ParseError::new(self.offset, cause)
+    }
+
+    pub fn source(&self) -> &S {
+        &self.source
+    }
+
+    pub fn into_inner(self) -> S {
+        self.source
+    }
+}
+
+impl<'a, S: Source<'a>> io::Read for Input<S> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.bits.left() != 0 {
+            let unaligned = Unaligned(self.bits.left()).compat();
+            return Err(io::Error::new(io::ErrorKind::InvalidData, unaligned));
+        }
+        let len = match self.end {
+            Some(end) => buf.len().min(end - self.offset),
+            None => buf.len(),
+        };
+        let n = self.source.read(&mut buf[..len])?;
+        self.offset += n;
+        Ok(n)
+    }
+}
+
+/// A type borrowing from the input was parsed from a source that can not lend its bytes.
+#[derive(Debug, Fail)]
+#[fail(display = "borrowing from the input needs a SliceSource")]
+pub struct NotBorrowable;
+
+/// One step of the path to the value that failed to parse.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Segment {
+    /// A named field, displayed as `.name`.
+    Field(&'static str),
+    /// A field of a tuple struct, displayed as `.0`.
+    Position(usize),
+    /// An element of a sequence, displayed as `[3]`.
+    Index(usize),
+    /// An enum variant, displayed as `::Name`.
+    Variant(&'static str),
+}
+
+impl fmt::Display for Segment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Segment::Field(name) => write!(f, ".{}", name),
+            Segment::Position(index) => write!(f, ".{}", index),
+            Segment::Index(index) => write!(f, "[{}]", index),
+            Segment::Variant(name) => write!(f, "::{}", name),
+        }
+    }
+}
+
+/// A parse failure, with the offset where it happened and the path to the failing value.
+///
+/// The path is built while the error travels up: each struct, field and variant the failing value
+/// was nested in adds itself.
+#[derive(Debug, Clone)]
+pub struct ParseError {
+    offset: usize,
+    type_name: Option<&'static str>,
+    /// Innermost segment first.
+    segments: Vec<Segment>,
+    /// Shared, so that `Memo` can hand out the same error again.
+    cause: Arc<Error>,
+}
+
+impl ParseError {
+    pub fn new(offset: usize, cause: impl Into<Error>) -> Self {
+        ParseError {
+            offset,
+            type_name: None,
+            segments: Vec::new(),
+            cause: Arc::new(cause.into()),
+        }
+    }
+
+    /// Byte offset where parsing failed.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// The underlying error, such as an `io::Error` for an unexpected end of input.
+    pub fn cause(&self) -> &Error {
+        &self.cause
+    }
+
+    /// Path to the failing value, like `Header.flags` or `Message::Ping.payload[3]`.
+    pub fn path(&self) -> String {
+        let mut result = self.type_name.unwrap_or_default().to_string();
+        for segment in self.segments.iter().rev() {
+            result.push_str(&segment.to_string());
+        }
+        result
+    }
+
+    /// Record that the failing value is nested in a value of type `name`.
+    pub fn in_type(mut self, name: &'static str) -> Self {
+        self.type_name = Some(name);
+        self
+    }
+
+    pub fn in_field(mut self, name: &'static str) -> Self {
+        self.segments.push(Segment::Field(name));
+        self
+    }
+
+    pub fn in_position(mut self, index: usize) -> Self {
+        self.segments.push(Segment::Position(index));
+        self
+    }
+
+    pub fn in_index(mut self, index: usize) -> Self {
+        self.segments.push(Segment::Index(index));
+        self
+    }
+
+    /// Record that the failing value is in the variant `M`, nested in a value of its enum.
+    pub fn in_variant<M: DestructEnumVariantMetadata>(mut self) -> Self {
+        // `variant_name` is qualified with the enum name, which becomes the type.
+        let name = M::variant_name();
+        let name = name.rsplit("::").next().unwrap_or(name);
+        self.segments.push(Segment::Variant(name));
+        self.in_type(M::enum_name())
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let path = self.path();
+        if path.is_empty() {
+            write!(f, "at offset {}: {}", self.offset, self.cause)
+        } else {
+            write!(f, "{} at offset {}: {}", path, self.offset, self.cause)
+        }
+    }
+}
+
+impl Fail for ParseError {
+    fn cause(&self) -> Option<&dyn Fail> {
+        Some(self.cause.as_fail())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    #[derive(Destruct, Debug, PartialEq)]
+    #[destruct(parsable)]
+    struct Header {
+        version: u8,
+        flags: u16,
+    }
+
+    #[derive(Destruct, Debug, PartialEq)]
+    #[destruct(parsable)]
+    struct Packet {
+        header: Header,
+        body: Pair,
+    }
+
+    #[derive(Destruct, Debug, PartialEq)]
+    #[destruct(parsable)]
+    struct Pair(u8, Validated<u8, IsAsciiDigit>);
+
+    fn parse_error<'a, T: Parsable<'a> + std::fmt::Debug>(bytes: &'a [u8]) -> ParseError {
+        let mut input = Input::new(SliceSource::new(bytes));
+        T::parse_input(&mut input).unwrap_err()
+    }
+
+    #[test]
+    fn test_error_path() {
+        let error = parse_error::<Header>(&[1, 0]);
+        assert_eq!(error.path(), "Header.flags");
+        assert_eq!(error.offset(), 2);
+
+        let error = parse_error::<Packet>(&[1, 0, 2, 7, b'x']);
+        assert_eq!(error.path(), "Packet.body.1");
+        assert_eq!(error.offset(), 4);
+        assert_eq!(
+            error.to_string(),
+            "Packet.body.1 at offset 4: Can not validate IsAsciiDigit"
+        );
+    }
+
+    #[test]
+    fn test_parse_keeps_error() {
+        let error = Packet::parse(&mut &[1, 0][..]).unwrap_err();
+        let error = error.downcast::<ParseError>().unwrap();
+        assert_eq!(error.path(), "Packet.header.flags");
+        assert!(error.cause().downcast_ref::<std::io::Error>().is_some());
+    }
+}
@@ -1,79 +1,400 @@
+#![allow(non_local_definitions)]
 #[macro_use]
 extern crate derive_new;
 #[allow(unused_imports)]
 #[macro_use]
 extern crate destruct;
 
+use crate::collection::parse_elements;
+use crate::dependent::parse_field;
+use crate::repeat::parse_repeated;
 use byteorder::ReadBytesExt;
 use destruct::*;
 use failure::{Error, Fail};
+use std::fmt;
 use std::io;
 use std::marker::PhantomData;
+use unicode_xid::UnicodeXID;
+
+mod bits;
+mod borrowed;
+mod checksum;
+mod collection;
+mod dependent;
+mod emit;
+mod input;
+mod memo;
+mod number;
+mod options;
+mod output;
+mod padding;
+mod repeat;
+mod skip;
+mod source;
+mod std_types;
+mod tag;
+mod tagged;
+mod text;
+mod validate;
+
+pub use crate::bits::*;
+pub use crate::borrowed::*;
+pub use crate::checksum::*;
+pub use crate::collection::*;
+pub use crate::dependent::*;
+pub use crate::emit::*;
+pub use crate::input::*;
+pub use crate::memo::*;
+pub use crate::number::*;
+pub use crate::options::*;
+pub use crate::output::*;
+pub use crate::padding::*;
+pub use crate::repeat::*;
+pub use crate::skip::*;
+pub use crate::source::*;
+pub use crate::tag::*;
+pub use crate::tagged::*;
+pub use crate::text::*;
+pub use crate::validate::*;
+
+/// Paths used by the expansions of the macros, so that callers need not depend on the crates.
+#[doc(hidden)]
+pub mod __private {
+    pub extern crate destruct;
+    pub extern crate failure;
+}
+
+pub trait Parsable<'a>: Sized {
+    /// Whether parsing reads back the bytes of the enclosing struct before the value, see
+    /// `Input::struct_bytes`. The struct then keeps them until it is parsed. Types holding other
+    /// values take it from them.
+    const NEEDS_STRUCT_BYTES: bool = false;
+
+    /// Parse from `input`, which tracks the offset and byte order.
+    fn parse_input<S: Source<'a>>(input: &mut Input<S>) -> Result<Self, ParseError>;
+
+    /// The value as a number, for the `len` and `count` of the fields after it, see
+    /// `#[destruct(meta(...))]`. `None` for values that are not numbers.
+    fn number(&self) -> Option<i128> {
+        None
+    }
+
+    /// Parse from `read`, buffering what may be needed for backtracking. Failures are
+    /// `ParseError`s.
+    ///
+    /// Bytes read ahead and then backtracked over are consumed from `read` all the same. Use an
+    /// `Input` over a `SliceSource` to find out where the value ended.
+    fn parse<R: io::Read>(read: &mut R) -> Result<Self, Error> {
+        let mut input = Input::new(BufferedSource::new(read));
+        Ok(Self::parse_input(&mut input)?)
+    }
 
-pub trait Parsable: Sized {
-    fn parse<R: io::Read + Clone>(read: &mut R) -> Result<Self, Error>;
+    /// Parse from the start of `bytes` and advance it past the value. Fields such as `&'a [u8]`
+    /// borrow from `bytes` instead of copying.
+    fn parse_slice(bytes: &mut &'a [u8]) -> Result<Self, Error> {
+        let mut input = Input::new(SliceSource::new(bytes));
+        let result = Self::parse_input(&mut input)?;
+        *bytes = input.into_inner().remaining();
+        Ok(result)
+    }
 }
 
-impl Parsable for u8 {
-    fn parse<R: io::Read + Clone>(read: &mut R) -> Result<Self, Error> {
-        read.read_u8().map_err(Into::into)
+impl<'a> Parsable<'a> for u8 {
+    fn parse_input<S: Source<'a>>(input: &mut Input<S>) -> Result<Self, ParseError> {
+        input.read_u8().map_err(|e| input.error(e))
+    }
+
+    fn number(&self) -> Option<i128> {
+        Some(i128::from(*self))
     }
 }
 
-impl<M: DestructMetadata + 'static> Parsable for DestructEnd<M> {
-    fn parse<R: io::Read + Clone>(_: &mut R) -> Result<Self, Error> {
+impl<'a, M: DestructMetadata + 'static> Parsable<'a> for DestructEnd<M> {
+    fn parse_input<S: Source<'a>>(_: &mut Input<S>) -> Result<Self, ParseError> {
         Ok(DestructEnd::new())
     }
 }
 
-impl<H: Parsable, T: Parsable, M: DestructFieldMetadata + 'static> Parsable
+impl<'a, H: Parsable<'a>, T: Parsable<'a>, M: DestructFieldMetadata + 'static> Parsable<'a>
     for DestructField<H, T, M>
 {
-    fn parse<R: io::Read + Clone>(read: &mut R) -> Result<Self, Error> {
-        Ok(DestructField::new(H::parse(read)?, T::parse(read)?))
+    const NEEDS_STRUCT_BYTES: bool = H::NEEDS_STRUCT_BYTES || T::NEEDS_STRUCT_BYTES;
+
+    fn parse_input<S: Source<'a>>(input: &mut Input<S>) -> Result<Self, ParseError> {
+        let head = input
+            .skip_ignored()
+            .and_then(|()| parse_field::<H, M, S>(input));
+        let head = head.map_err(|e| {
+            if M::named_fields() {
+                e.in_field(M::field_name())
+            } else {
+                e.in_position(M::field_index())
+            }
+        })?;
+        Ok(DestructField::new(head, T::parse_input(input)?))
     }
 }
 
-impl<F: Parsable, M: DestructMetadata + 'static> Parsable for DestructBegin<F, M> {
-    fn parse<R: io::Read + Clone>(read: &mut R) -> Result<Self, Error> {
-        Ok(DestructBegin::new(F::parse(read)?))
+impl<'a, F: Parsable<'a>, M: DestructMetadata + 'static> Parsable<'a> for DestructBegin<F, M> {
+    fn parse_input<S: Source<'a>>(input: &mut Input<S>) -> Result<Self, ParseError> {
+        // A choice given to a struct must not pick the variant of the first enum in its fields.
+        input.take_choice();
+        let mark = if F::NEEDS_STRUCT_BYTES {
+            Some(input.mark())
+        } else {
+            None
+        };
+        let result = input
+            .in_scope(F::NEEDS_STRUCT_BYTES, F::parse_input)
+            .and_then(|fields| input.check_aligned().map(|()| fields));
+        if let Some(mark) = mark {
+            input.release(mark);
+        }
+        result
+            .map(DestructBegin::new)
+            .map_err(|e| e.in_type(M::struct_name()))
     }
 }
 
-#[derive(Debug, Fail)]
-#[fail(display = "Can not parse enum {}", 0)]
-pub struct EnumParseError(&'static str);
+/// One enum variant that failed to parse.
+#[derive(Debug)]
+pub struct VariantFailure {
+    pub variant: &'static str,
+    pub error: ParseError,
+}
+
+impl VariantFailure {
+    /// How far the variant got before failing.
+    pub fn offset(&self) -> usize {
+        self.error.offset()
+    }
+}
+
+/// No variant of an enum matched. Lists every variant that was tried, furthest progress first.
+#[derive(Debug)]
+pub struct EnumParseError {
+    enum_name: &'static str,
+    failures: Vec<VariantFailure>,
+}
+
+impl EnumParseError {
+    pub fn enum_name(&self) -> &'static str {
+        self.enum_name
+    }
+
+    pub fn failures(&self) -> &[VariantFailure] {
+        &self.failures
+    }
+}
+
+impl fmt::Display for EnumParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Can not parse enum {}", self.enum_name)?;
+        for failure in &self.failures {
+            write!(f, "\n  {}", failure.error)?;
+        }
+        Ok(())
+    }
+}
 
-impl<M: DestructEnumMetadata + 'static> Parsable for DestructEnumEnd<M> {
-    fn parse<R: io::Read + Clone>(_: &mut R) -> Result<Self, Error> {
-        Err(EnumParseError(M::enum_name()).into())
+impl Fail for EnumParseError {}
+
+/// How a derived enum picks among its variants, set with the `ordered`, `longest` and `strict`
+/// options.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Choice {
+    /// The first variant that parses.
+    #[default]
+    Ordered,
+    /// The variant that parses the most bytes, the first of them on a tie.
+    Longest,
+    /// The only variant that parses, failing with `AmbiguousEnum` if several do. Meant for catching
+    /// ambiguous grammars during development.
+    Strict,
+}
+
+/// Several variants of an enum parsed the same input, with `Choice::Strict`.
+#[derive(Debug)]
+pub struct AmbiguousEnum {
+    pub enum_name: &'static str,
+    pub variants: Vec<&'static str>,
+}
+
+impl fmt::Display for AmbiguousEnum {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Ambiguous enum {}, variants {} all parse",
+            self.enum_name,
+            self.variants.join(", ")
+        )
     }
 }
 
-impl<H: Parsable, T: Parsable, M: DestructEnumVariantMetadata + 'static> Parsable
-    for DestructEnumVariant<H, T, M>
+impl Fail for AmbiguousEnum {}
+
+/// A variant that parsed, found by `ParsableVariants::match_variants`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VariantMatch {
+    pub variant: &'static str,
+    pub index: usize,
+    /// Offset the variant ended at.
+    pub end: usize,
+}
+
+/// Parsing over the variant list of an enum.
+///
+/// Implemented for `DestructEnumVariant` and `DestructEnumEnd`.
+pub trait ParsableVariants<'a>: Sized {
+    /// Try the variants in order, returning the first that parses. Every variant that fails is
+    /// pushed to `failures`, and `input` is rewound before the next one is tried.
+    ///
+    /// Fails only if `input` can not be rewound.
+    fn parse_variants<S: Source<'a>>(
+        input: &mut Input<S>,
+        failures: &mut Vec<VariantFailure>,
+    ) -> Result<Option<Self>, ParseError>;
+
+    /// Try every variant from the current offset, rewinding after each. Pushes the variants that
+    /// parse to `matches` and the others to `failures`.
+    fn match_variants<S: Source<'a>>(
+        input: &mut Input<S>,
+        failures: &mut Vec<VariantFailure>,
+        matches: &mut Vec<VariantMatch>,
+    ) -> Result<(), ParseError>;
+
+    /// Parse the variant with the given `variant_index`.
+    fn parse_variant_at<S: Source<'a>>(
+        input: &mut Input<S>,
+        index: usize,
+    ) -> Result<Self, ParseError>;
+}
+
+impl<'a, M: DestructEnumMetadata + 'static> ParsableVariants<'a> for DestructEnumEnd<M> {
+    fn parse_variants<S: Source<'a>>(
+        _: &mut Input<S>,
+        _: &mut Vec<VariantFailure>,
+    ) -> Result<Option<Self>, ParseError> {
+        Ok(None)
+    }
+
+    fn match_variants<S: Source<'a>>(
+        _: &mut Input<S>,
+        _: &mut Vec<VariantFailure>,
+        _: &mut Vec<VariantMatch>,
+    ) -> Result<(), ParseError> {
+        Ok(())
+    }
+
+    fn parse_variant_at<S: Source<'a>>(_: &mut Input<S>, _: usize) -> Result<Self, ParseError> {
+        unreachable!()
+    }
+}
+
+impl<'a, H: Parsable<'a>, T: ParsableVariants<'a>, M: DestructEnumVariantMetadata + 'static>
+    ParsableVariants<'a> for DestructEnumVariant<H, T, M>
 {
-    fn parse<R: io::Read + Clone>(read: &mut R) -> Result<Self, Error> {
-        let backup = read.clone();
-        match H::parse(read) {
-            Ok(r) => Ok(DestructEnumVariant::new_head(r)),
-            Err(_) => {
-                *read = backup;
-                Ok(DestructEnumVariant::new_tail(T::parse(read)?))
+    fn parse_variants<S: Source<'a>>(
+        input: &mut Input<S>,
+        failures: &mut Vec<VariantFailure>,
+    ) -> Result<Option<Self>, ParseError> {
+        let mark = input.mark();
+        match H::parse_input(input) {
+            Ok(r) => {
+                input.release(mark);
+                Ok(Some(DestructEnumVariant::new_head(r)))
             }
+            Err(error) => {
+                failures.push(VariantFailure {
+                    variant: M::variant_name(),
+                    error,
+                });
+                input.rewind(mark)?;
+                Ok(T::parse_variants(input, failures)?.map(DestructEnumVariant::new_tail))
+            }
+        }
+    }
+
+    fn match_variants<S: Source<'a>>(
+        input: &mut Input<S>,
+        failures: &mut Vec<VariantFailure>,
+        matches: &mut Vec<VariantMatch>,
+    ) -> Result<(), ParseError> {
+        let mark = input.mark();
+        match H::parse_input(input) {
+            Ok(_) => matches.push(VariantMatch {
+                variant: M::variant_name(),
+                index: M::variant_index(),
+                end: input.offset(),
+            }),
+            Err(error) => failures.push(VariantFailure {
+                variant: M::variant_name(),
+                error,
+            }),
+        }
+        input.rewind(mark)?;
+        T::match_variants(input, failures, matches)
+    }
+
+    fn parse_variant_at<S: Source<'a>>(
+        input: &mut Input<S>,
+        index: usize,
+    ) -> Result<Self, ParseError> {
+        if M::variant_index() == index {
+            H::parse_input(input)
+                .map(DestructEnumVariant::new_head)
+                .map_err(|e| e.in_variant::<M>())
+        } else {
+            T::parse_variant_at(input, index).map(DestructEnumVariant::new_tail)
         }
     }
 }
 
-impl<T: Parsable, M: DestructEnumMetadata + 'static> Parsable for DestructEnumBegin<T, M> {
-    fn parse<R: io::Read + Clone>(read: &mut R) -> Result<Self, Error> {
-        Ok(DestructEnumBegin::new(T::parse(read)?))
+/// With `Choice::Longest` and `Choice::Strict`, the chosen variant is parsed a second time after
+/// all of them were tried.
+impl<'a, T: ParsableVariants<'a>, M: DestructEnumMetadata + 'static> Parsable<'a>
+    for DestructEnumBegin<T, M>
+{
+    fn parse_input<S: Source<'a>>(input: &mut Input<S>) -> Result<Self, ParseError> {
+        let mut failures = Vec::new();
+        let choice = input.take_choice();
+        let variants = if choice == Choice::Ordered {
+            T::parse_variants(input, &mut failures)?
+        } else {
+            let mut matches = Vec::new();
+            T::match_variants(input, &mut failures, &mut matches)?;
+            if choice == Choice::Strict && matches.len() > 1 {
+                let error = AmbiguousEnum {
+                    enum_name: M::enum_name(),
+                    variants: matches.iter().map(|m| m.variant).collect(),
+                };
+                return Err(input.error(error).in_type(M::enum_name()));
+            }
+            // `max_by_key` returns the last maximum, reversing keeps the first variant on a tie.
+            match matches.iter().rev().max_by_key(|m| m.end) {
+                Some(m) => Some(T::parse_variant_at(input, m.index)?),
+                None => None,
+            }
+        };
+        match variants {
+            Some(variants) => Ok(DestructEnumBegin::new(variants)),
+            None => {
+                // Stable, so variants that got equally far stay in declaration order.
+                failures.sort_by_key(|failure| std::cmp::Reverse(failure.offset()));
+                let error = EnumParseError {
+                    enum_name: M::enum_name(),
+                    failures,
+                };
+                Err(input.error(error).in_type(M::enum_name()))
+            }
+        }
     }
 }
 
 pub trait Validator<T: Sized> {
     fn validate(value: &T) -> bool;
-    fn description() -> &'static str;
+    /// What valid values are, for `ValidateError`.
+    fn description() -> String;
 }
 
 #[derive(new, Debug, PartialEq, Eq)]
@@ -85,32 +406,50 @@ pub struct Validated<T, F: Validator<T> + 'static> {
 
 #[derive(Debug, Fail)]
 #[fail(display = "Can not validate {}", 0)]
-pub struct ValidateError(&'static str);
-
-impl<T: Parsable, F: Validator<T>> Parsable for Validated<T, F> {
-    fn parse<R: io::Read + Clone>(read: &mut R) -> Result<Self, Error> {
-        let backup = read.clone();
-        let r = T::parse(read)?;
+pub struct ValidateError(pub String);
+
+impl<'a, T: Parsable<'a>, F: Validator<T>> Parsable<'a> for Validated<T, F> {
+    const NEEDS_STRUCT_BYTES: bool = T::NEEDS_STRUCT_BYTES;
+
+    fn parse_input<S: Source<'a>>(input: &mut Input<S>) -> Result<Self, ParseError> {
+        let mark = input.mark();
+        let r = match T::parse_input(input) {
+            Ok(r) => r,
+            Err(e) => {
+                input.release(mark);
+                return Err(e);
+            }
+        };
         if F::validate(&r) {
+            input.release(mark);
             Ok(Validated::new(r))
         } else {
-            *read = backup;
-            Err(ValidateError(F::description()).into())
+            input.rewind(mark)?;
+            Err(input.error(ValidateError(F::description())))
         }
     }
+
+    fn number(&self) -> Option<i128> {
+        self.value.number()
+    }
 }
 
+/// Define a `Validator` type, described by its name.
+///
+/// `define_validator!(IsEven, |value: &u8| value % 2 == 0)` defines `IsEven`, for fields
+/// of type `Validated<u8, IsEven>`.
+#[macro_export]
 macro_rules! define_validator {
     ($name:ident, |$value:ident : &$t:ty| $e:expr) => {
         #[derive(Debug, PartialEq, Eq)]
         pub struct $name;
 
-        impl Validator<$t> for $name {
+        impl $crate::Validator<$t> for $name {
             fn validate($value: &$t) -> bool {
                 $e
             }
-            fn description() -> &'static str {
-                stringify!($name)
+            fn description() -> String {
+                stringify!($name).to_string()
             }
         }
     };
@@ -121,33 +460,76 @@ define_validator!(IsAsciiLowerCase, |value: &u8| *value >= b'a'
     && *value <= b'z');
 define_validator!(IsAsciiUpperCase, |value: &u8| *value >= b'A'
     && *value <= b'Z');
-
-impl<T: Parsable> Parsable for Vec<T> {
-    fn parse<R: io::Read + Clone>(read: &mut R) -> Result<Self, Error> {
-        let mut result = Vec::new();
-        loop {
-            let backup = read.clone();
-            match T::parse(read) {
-                Ok(i) => result.push(i),
-                Err(_) => {
-                    *read = backup;
-                    return Ok(result);
-                }
-            }
+define_validator!(IsAlphabetic, |value: &char| value.is_alphabetic());
+define_validator!(IsNumeric, |value: &char| value.is_numeric());
+define_validator!(IsWhitespace, |value: &char| value.is_whitespace());
+define_validator!(IsXidStart, |value: &char| UnicodeXID::is_xid_start(*value));
+define_validator!(IsXidContinue, |value: &char| UnicodeXID::is_xid_continue(
+    *value
+));
+
+/// As many elements as parse, possibly none, or exactly the `count` of the field, see
+/// `Input::take_count`.
+impl<'a, T: Parsable<'a>> Parsable<'a> for Vec<T> {
+    const NEEDS_STRUCT_BYTES: bool = T::NEEDS_STRUCT_BYTES;
+
+    fn parse_input<S: Source<'a>>(input: &mut Input<S>) -> Result<Self, ParseError> {
+        match input.take_count() {
+            Some(count) => parse_elements(input, count),
+            None => parse_repeated(input, usize::MAX).map(|(result, _)| result),
         }
     }
 }
 
 /// Use macros to workaround overlapping impls
+///
+/// Options follow the type, see `Options`: `parsable!(T, big_endian)` and
+/// `parsable!(T, little_endian)` set the byte order of the numbers in `T` and the structs nested in
+/// it. Without it, `T` uses the byte order of the struct it is nested in, and `Endian::default()`
+/// at the top.
+///
+/// A type with a lifetime, `parsable!(T<'a>)`, may borrow from the input.
+///
+/// An enum with `tag(u8)` as its first option is preceded by a tag selecting the variant, see
+/// `Tagged`.
 #[macro_export]
 macro_rules! parsable {
-    ($t:ident) => {
-        impl Parsable for $t {
-            fn parse<R: io::Read + Clone>(read: &mut R) -> Result<Self, Error> {
-                <$t as Destruct>::DestructType::parse(read).map(<$t as Destruct>::construct)
+    ($t:ident < $lt:lifetime > $(, $($options:tt)*)?) => {
+        impl<$lt> $crate::Parsable<$lt> for $t<$lt> {
+            fn parse_input<S: $crate::Source<$lt>>(
+                input: &mut $crate::Input<S>,
+            ) -> Result<Self, $crate::ParseError> {
+                $crate::parsable!(@parse $t, $t<$lt>, $lt, input, $($($options)*)?)
+            }
+        }
+    };
+    ($t:ident $(, $($options:tt)*)?) => {
+        impl<'a> $crate::Parsable<'a> for $t {
+            fn parse_input<S: $crate::Source<'a>>(
+                input: &mut $crate::Input<S>,
+            ) -> Result<Self, $crate::ParseError> {
+                $crate::parsable!(@parse $t, $t, 'a, input, $($($options)*)?)
             }
         }
     };
+    (@parse $name:ident, $t:ty, $lt:lifetime, $input:ident, tag($tag:ty) $(, $($options:tt)*)?) => {
+        $crate::parser_options!($($($options)*)?)
+            .parse(
+                stringify!($name),
+                $input,
+                <$crate::Tagged<$tag, <$t as $crate::__private::destruct::Destruct>::DestructType> as $crate::Parsable<$lt>>::parse_input,
+            )
+            .map(|tagged| <$t as $crate::__private::destruct::Destruct>::construct(tagged.value))
+    };
+    (@parse $name:ident, $t:ty, $lt:lifetime, $input:ident, $($options:tt)*) => {
+        $crate::parser_options!($($options)*)
+            .parse(
+                stringify!($name),
+                $input,
+                <<$t as $crate::__private::destruct::Destruct>::DestructType as $crate::Parsable<$lt>>::parse_input,
+            )
+            .map(<$t as $crate::__private::destruct::Destruct>::construct)
+    };
 }
 
 #[cfg(test)]
@@ -276,4 +658,100 @@ mod tests {
         let result = Identifier::parse(&mut s3.as_ref());
         assert!(result.is_err())
     }
+
+    #[derive(Debug, Destruct, PartialEq, Eq)]
+    #[destruct(parsable)]
+    enum Message {
+        Ping(Validated<u8, IsAsciiLowerCase>, u8),
+        Pong {
+            id: u8,
+            payload: Validated<u8, IsAsciiDigit>,
+        },
+    }
+
+    #[test]
+    fn test_enum_failures() {
+        let error = Message::parse(&mut b"7x".as_ref()).unwrap_err();
+        let error = error.downcast::<ParseError>().unwrap();
+        assert_eq!(error.path(), "Message");
+        let failures = error
+            .cause()
+            .downcast_ref::<EnumParseError>()
+            .unwrap()
+            .failures();
+        assert_eq!(failures[0].variant, "Message::Pong");
+        assert_eq!(failures[0].offset(), 1);
+        assert_eq!(failures[1].variant, "Message::Ping");
+        assert_eq!(failures[1].offset(), 0);
+        assert_eq!(
+            error.to_string(),
+            "Message at offset 0: Can not parse enum Message\n  \
+             Message::Pong.payload at offset 1: Can not validate IsAsciiDigit\n  \
+             Message::Ping.0 at offset 0: Can not validate IsAsciiLowerCase"
+        );
+    }
+
+    #[derive(Debug, Destruct, PartialEq, Eq)]
+    #[destruct(parsable(longest))]
+    enum Longest {
+        Short(Validated<u8, IsAsciiLowerCase>),
+        Long(
+            Validated<u8, IsAsciiLowerCase>,
+            Validated<u8, IsAsciiLowerCase>,
+        ),
+    }
+
+    #[derive(Debug, Destruct, PartialEq, Eq)]
+    #[destruct(parsable(strict))]
+    enum Strict {
+        Short(Validated<u8, IsAsciiLowerCase>),
+        Long(
+            Validated<u8, IsAsciiLowerCase>,
+            Validated<u8, IsAsciiLowerCase>,
+        ),
+    }
+
+    #[derive(Debug, Destruct, PartialEq, Eq)]
+    #[destruct(parsable)]
+    enum Letters {
+        One(Validated<u8, IsAsciiLowerCase>),
+        Two(
+            Validated<u8, IsAsciiLowerCase>,
+            Validated<u8, IsAsciiLowerCase>,
+        ),
+    }
+
+    // The derive rejects `parsable(longest)` on a struct, the macro ignores it.
+    #[derive(Debug, Destruct, PartialEq, Eq)]
+    struct Word {
+        letters: Letters,
+    }
+
+    parsable!(Word, longest);
+
+    #[test]
+    fn test_choice() {
+        let mut bytes = &b"ab1"[..];
+        let result = Longest::parse_slice(&mut bytes).unwrap();
+        assert_eq!(
+            result,
+            Longest::Long(Validated::new(b'a'), Validated::new(b'b'))
+        );
+        assert_eq!(bytes, b"1");
+        let result = Longest::parse_slice(&mut &b"a1"[..]).unwrap();
+        assert_eq!(result, Longest::Short(Validated::new(b'a')));
+
+        let result = Strict::parse_slice(&mut &b"a1"[..]).unwrap();
+        assert_eq!(result, Strict::Short(Validated::new(b'a')));
+        let error = Strict::parse_slice(&mut &b"ab"[..]).unwrap_err();
+        let error = error.downcast::<ParseError>().unwrap();
+        assert_eq!(
+            error.to_string(),
+            "Strict at offset 0: Ambiguous enum Strict, variants Strict::Short, Strict::Long all parse"
+        );
+        assert!(Strict::parse_slice(&mut &b"1"[..]).is_err());
+
+        let word = Word::parse_slice(&mut &b"ab"[..]).unwrap();
+        assert_eq!(word.letters, Letters::One(Validated::new(b'a')));
+    }
 }
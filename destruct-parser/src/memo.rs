@@ -0,0 +1,219 @@
+//! Packrat memoization: remembering what a type parsed to at an offset, so that backtracking
+//! does not parse it again.
+//!
+//! ```ignore
+//! #[derive(Destruct, Clone)]
+//! #[destruct(parsable)]
+//! enum Expr {
+//!     Add(Term, Plus, Box<Memo<Expr>>),
+//!     Sub(Term, Minus, Box<Memo<Expr>>),
+//!     Term(Term),
+//! }
+//!
+//! let mut input = Input::new(SliceSource::new(bytes)).memoize(10_000);
+//! let expr = Memo::<Expr>::parse_input(&mut input)?;
+//! ```
+//!
+//! Only `Memo<T>` values are remembered, and only by an `Input` with a table from
+//! `Input::memoize`; otherwise `Memo<T>` parses `T` as usual. `T` is cloned out of the table, and
+//! must not borrow from the input.
+
+use crate::{
+    BitOrder, Emit, Endian, Input, Output, Parsable, ParseError, Redaction, Skipper, Source,
+};
+use failure::Error;
+use std::any::{Any, TypeId};
+use std::collections::{HashMap, VecDeque};
+use std::io;
+
+/// A `T` whose parse results are remembered by `Input::memoize`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Memo<T>(pub T);
+
+/// What a type parses to from an offset does not depend on how it got there, except for the byte
+/// and bit orders, the skipper, the end of the input and the `count` of a dependent field.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct MemoKey {
+    type_id: TypeId,
+    offset: usize,
+    order: Endian,
+    bit_order: BitOrder,
+    skipper: Skipper,
+    end: Option<usize>,
+    count: Option<usize>,
+}
+
+/// A parsed value and the offset it ends at, or the error it failed with.
+type MemoEntry = Result<(Box<dyn Any>, usize), ParseError>;
+
+/// The results remembered by an `Input`, dropping the oldest beyond `capacity` entries.
+#[derive(Debug)]
+pub(crate) struct MemoTable {
+    capacity: usize,
+    entries: HashMap<MemoKey, MemoEntry>,
+    /// Keys from oldest to newest.
+    keys: VecDeque<MemoKey>,
+}
+
+impl MemoTable {
+    pub(crate) fn new(capacity: usize) -> Self {
+        MemoTable {
+            capacity,
+            entries: HashMap::new(),
+            keys: VecDeque::new(),
+        }
+    }
+
+    fn insert(&mut self, key: MemoKey, entry: MemoEntry) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.entries.len() == self.capacity {
+            if let Some(oldest) = self.keys.pop_front() {
+                self.entries.remove(&oldest);
+            }
+        }
+        if self.entries.insert(key, entry).is_none() {
+            self.keys.push_back(key);
+        }
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.entries.len()
+    }
+}
+
+impl<'a, T: Parsable<'a> + Clone + 'static> Parsable<'a> for Memo<T> {
+    const NEEDS_STRUCT_BYTES: bool = T::NEEDS_STRUCT_BYTES;
+
+    fn parse_input<S: Source<'a>>(input: &mut Input<S>) -> Result<Self, ParseError> {
+        let key = MemoKey {
+            type_id: TypeId::of::<T>(),
+            offset: input.offset(),
+            order: input.order(),
+            bit_order: input.bit_order(),
+            skipper: input.skipper(),
+            end: input.end(),
+            count: input.dependencies().count(),
+        };
+        // Results are remembered by byte offset, so only between bytes.
+        let aligned = input.bit_cursor().left() == 0;
+        let hit = match input.memo() {
+            Some(table) if aligned => table.entries.get(&key).map(|entry| match entry {
+                Ok((value, end)) => Ok((value.downcast_ref::<T>().cloned(), *end)),
+                Err(error) => Err(error.clone()),
+            }),
+            _ => return T::parse_input(input).map(Memo),
+        };
+        match hit {
+            Some(Ok((Some(value), end))) => {
+                input.skip_to(end)?;
+                Ok(Memo(value))
+            }
+            Some(Ok((None, _))) => unreachable!(),
+            Some(Err(error)) => Err(error),
+            None => {
+                let result = T::parse_input(input);
+                let entry = match &result {
+                    Ok(value) => Ok((Box::new(value.clone()) as Box<dyn Any>, input.offset())),
+                    Err(error) => Err(error.clone()),
+                };
+                let aligned = input.bit_cursor().left() == 0;
+                match input.memo_mut() {
+                    Some(table) if aligned => table.insert(key, entry),
+                    _ => {}
+                }
+                result.map(Memo)
+            }
+        }
+    }
+
+    fn number(&self) -> Option<i128> {
+        self.0.number()
+    }
+}
+
+impl<T: Emit> Emit for Memo<T> {
+    fn emit<W: io::Write>(&self, write: &mut W) -> Result<(), Error> {
+        self.0.emit(write)
+    }
+
+    fn emit_ordered<W: io::Write>(&self, write: &mut W, order: Endian) -> Result<(), Error> {
+        self.0.emit_ordered(write, order)
+    }
+
+    fn emit_in_struct(
+        &self,
+        bytes: &mut Output,
+        start: Option<usize>,
+        order: Endian,
+        redaction: Redaction,
+    ) -> Result<(), Error> {
+        self.0.emit_in_struct(bytes, start, order, redaction)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+    use std::cell::Cell;
+
+    thread_local! {
+        static PARSED: Cell<usize> = const { Cell::new(0) };
+    }
+
+    /// A digit that counts how often it is parsed.
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    struct Digit(u8);
+
+    impl<'a> Parsable<'a> for Digit {
+        fn parse_input<S: Source<'a>>(input: &mut Input<S>) -> Result<Self, ParseError> {
+            PARSED.with(|parsed| parsed.set(parsed.get() + 1));
+            Validated::<u8, IsAsciiDigit>::parse_input(input).map(|_| Digit(0))
+        }
+    }
+
+    /// Both bracketed variants parse the same inner `Nested` before failing or succeeding on
+    /// the sign, so without memoization each level doubles the work.
+    #[derive(Destruct, Debug, Clone, PartialEq, Eq)]
+    #[destruct(parsable)]
+    enum Nested {
+        Plus(
+            Literal<b'('>,
+            Memo<Box<Nested>>,
+            Literal<b')'>,
+            Literal<b'+'>,
+        ),
+        Minus(
+            Literal<b'('>,
+            Memo<Box<Nested>>,
+            Literal<b')'>,
+            Literal<b'-'>,
+        ),
+        Leaf(Digit),
+    }
+
+    fn parse(bytes: &[u8], capacity: Option<usize>) -> (Result<Nested, ParseError>, usize) {
+        PARSED.with(|parsed| parsed.set(0));
+        let mut input = Input::new(SliceSource::new(bytes));
+        if let Some(capacity) = capacity {
+            input = input.memoize(capacity);
+        }
+        let result = Nested::parse_input(&mut input);
+        (result, PARSED.with(Cell::get))
+    }
+
+    #[test]
+    fn test_memo() {
+        let bytes = b"(((1)-)-)-";
+        let (plain, plain_count) = parse(bytes, None);
+        let (memo, memo_count) = parse(bytes, Some(100));
+        assert_eq!(plain.unwrap(), memo.unwrap());
+        assert_eq!(plain_count, 8);
+        assert_eq!(memo_count, 1);
+
+        // Keeping only the latest result is enough for this grammar, keeping none is not.
+        assert_eq!(parse(bytes, Some(1)).1, 1);
+        assert_eq!(parse(bytes, Some(0)).1, 8);
+    }
+}
@@ -0,0 +1,238 @@
+use crate::{Emit, Input, Output, Parsable, ParseError, Redaction, Source};
+use byteorder::{BigEndian as BE, LittleEndian as LE, ReadBytesExt, WriteBytesExt};
+use failure::Error;
+use std::convert::TryInto;
+use std::io;
+
+/// Byte order of multi-byte numbers.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Endian {
+    /// Most significant byte first, the network byte order.
+    #[default]
+    Big,
+    Little,
+}
+
+macro_rules! parsable_number {
+    ($($t:ty => $read:ident, $write:ident, $number:ident),* $(,)?) => {
+        $(
+            impl<'a> Parsable<'a> for $t {
+                fn parse_input<S: Source<'a>>(
+                    input: &mut Input<S>,
+                ) -> Result<Self, ParseError> {
+                    match input.order() {
+                        Endian::Big => input.$read::<BE>(),
+                        Endian::Little => input.$read::<LE>(),
+                    }
+                    .map_err(|e| input.error(e))
+                }
+
+                fn number(&self) -> Option<i128> {
+                    $number(*self)
+                }
+            }
+
+            impl Emit for $t {
+                fn emit<W: io::Write>(&self, write: &mut W) -> Result<(), Error> {
+                    self.emit_ordered(write, Endian::default())
+                }
+
+                fn emit_ordered<W: io::Write>(
+                    &self,
+                    write: &mut W,
+                    order: Endian,
+                ) -> Result<(), Error> {
+                    match order {
+                        Endian::Big => write.$write::<BE>(*self)?,
+                        Endian::Little => write.$write::<LE>(*self)?,
+                    }
+                    Ok(())
+                }
+            }
+        )*
+    };
+}
+
+fn integer<T: TryInto<i128>>(value: T) -> Option<i128> {
+    value.try_into().ok()
+}
+
+fn float<T>(_: T) -> Option<i128> {
+    None
+}
+
+parsable_number!(
+    u16 => read_u16, write_u16, integer,
+    u32 => read_u32, write_u32, integer,
+    u64 => read_u64, write_u64, integer,
+    u128 => read_u128, write_u128, integer,
+    i16 => read_i16, write_i16, integer,
+    i32 => read_i32, write_i32, integer,
+    i64 => read_i64, write_i64, integer,
+    i128 => read_i128, write_i128, integer,
+    f32 => read_f32, write_f32, float,
+    f64 => read_f64, write_f64, float,
+);
+
+impl<'a> Parsable<'a> for i8 {
+    fn parse_input<S: Source<'a>>(input: &mut Input<S>) -> Result<Self, ParseError> {
+        input.read_i8().map_err(|e| input.error(e))
+    }
+
+    fn number(&self) -> Option<i128> {
+        Some(i128::from(*self))
+    }
+}
+
+impl Emit for i8 {
+    fn emit<W: io::Write>(&self, write: &mut W) -> Result<(), Error> {
+        write.write_i8(*self)?;
+        Ok(())
+    }
+}
+
+/// A value always parsed in big endian, whatever the byte order of the enclosing struct.
+#[derive(new, Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct BigEndian<T>(pub T);
+
+/// A value always parsed in little endian, whatever the byte order of the enclosing struct.
+#[derive(new, Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct LittleEndian<T>(pub T);
+
+impl<'a, T: Parsable<'a>> Parsable<'a> for BigEndian<T> {
+    const NEEDS_STRUCT_BYTES: bool = T::NEEDS_STRUCT_BYTES;
+
+    fn parse_input<S: Source<'a>>(input: &mut Input<S>) -> Result<Self, ParseError> {
+        input.with_order(Endian::Big, T::parse_input).map(BigEndian)
+    }
+
+    fn number(&self) -> Option<i128> {
+        self.0.number()
+    }
+}
+
+impl<'a, T: Parsable<'a>> Parsable<'a> for LittleEndian<T> {
+    const NEEDS_STRUCT_BYTES: bool = T::NEEDS_STRUCT_BYTES;
+
+    fn parse_input<S: Source<'a>>(input: &mut Input<S>) -> Result<Self, ParseError> {
+        input
+            .with_order(Endian::Little, T::parse_input)
+            .map(LittleEndian)
+    }
+
+    fn number(&self) -> Option<i128> {
+        self.0.number()
+    }
+}
+
+impl<T: Emit> Emit for BigEndian<T> {
+    fn emit<W: io::Write>(&self, write: &mut W) -> Result<(), Error> {
+        self.0.emit_ordered(write, Endian::Big)
+    }
+
+    fn emit_in_struct(
+        &self,
+        bytes: &mut Output,
+        start: Option<usize>,
+        _: Endian,
+        redaction: Redaction,
+    ) -> Result<(), Error> {
+        self.0.emit_in_struct(bytes, start, Endian::Big, redaction)
+    }
+}
+
+impl<T: Emit> Emit for LittleEndian<T> {
+    fn emit<W: io::Write>(&self, write: &mut W) -> Result<(), Error> {
+        self.0.emit_ordered(write, Endian::Little)
+    }
+
+    fn emit_in_struct(
+        &self,
+        bytes: &mut Output,
+        start: Option<usize>,
+        _: Endian,
+        redaction: Redaction,
+    ) -> Result<(), Error> {
+        self.0
+            .emit_in_struct(bytes, start, Endian::Little, redaction)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parsable, SliceSource};
+    use destruct::*;
+
+    #[derive(Destruct, Debug, PartialEq)]
+    #[destruct(parsable(little_endian))]
+    struct Header {
+        magic: u16,
+        length: u32,
+        scale: f32,
+        port: BigEndian<u16>,
+    }
+
+    #[derive(Destruct, Debug, PartialEq)]
+    #[destruct(parsable)]
+    struct Inherits {
+        value: u16,
+    }
+
+    #[derive(Destruct, Debug, PartialEq)]
+    #[destruct(parsable(little_endian))]
+    struct Outer {
+        inner: Inherits,
+        fixed: Fixed,
+    }
+
+    #[derive(Destruct, Debug, PartialEq)]
+    #[destruct(parsable(big_endian))]
+    struct Fixed {
+        value: u16,
+    }
+
+    #[test]
+    fn test_numbers() {
+        let mut s: &[u8] = &[0x12, 0x34, 0xff, 0xfe];
+        assert_eq!(u16::parse(&mut s).unwrap(), 0x1234);
+        let mut input = Input::new(SliceSource::new(s));
+        let value = input.with_order(Endian::Little, i16::parse_input).unwrap();
+        assert_eq!(value, -257);
+        let mut s: &[u8] = &[0x01, 0x00];
+        assert_eq!(LittleEndian::<u16>::parse(&mut s).unwrap(), LittleEndian(1));
+        let mut s: &[u8] = &[0x01];
+        assert!(u16::parse(&mut s).is_err());
+    }
+
+    #[test]
+    fn test_struct_order() {
+        let mut s: &[u8] = &[
+            0x4d, 0x5a, 0x10, 0x00, 0x00, 0x00, 0x00, 0x00, 0xc0, 0x3f, 0x1f, 0x90,
+        ];
+        assert_eq!(
+            Header::parse(&mut s).unwrap(),
+            Header {
+                magic: 0x5a4d,
+                length: 16,
+                scale: 1.5,
+                port: BigEndian(8080),
+            }
+        );
+        assert!(s.is_empty());
+    }
+
+    #[test]
+    fn test_nested_order() {
+        let mut s: &[u8] = &[0x01, 0x00, 0x00, 0x01];
+        assert_eq!(
+            Outer::parse(&mut s).unwrap(),
+            Outer {
+                inner: Inherits { value: 1 },
+                fixed: Fixed { value: 1 },
+            }
+        );
+        let mut s: &[u8] = &[0x00, 0x01];
+        assert_eq!(Inherits::parse(&mut s).unwrap(), Inherits { value: 1 });
+    }
+}
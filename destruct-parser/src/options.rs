@@ -0,0 +1,157 @@
+use crate::tag::parse_tag;
+use crate::{
+    BitOrder, Choice, Emit, Endian, Input, Output, ParseError, Redaction, Skipper, Source,
+};
+use failure::Error;
+
+/// Options of a derived parser, given as `#[destruct(parsable(big_endian))]` or
+/// `parsable!(T, big_endian)`.
+///
+/// Every option is a method, the macros call them in order on `Options::new()`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Options {
+    /// Byte order of the numbers in the struct, inherited from the enclosing struct when `None`.
+    pub order: Option<Endian>,
+    /// Order of the bits of bit fields, inherited from the enclosing struct when `None`.
+    pub bit_order: Option<BitOrder>,
+    /// Bytes that must precede the fields, such as a file signature.
+    pub magic: Option<&'static [u8]>,
+    /// How an enum picks among its variants.
+    pub choice: Choice,
+    /// What is skipped before each field, inherited from the enclosing struct when `None`.
+    pub skipper: Option<Skipper>,
+}
+
+impl Options {
+    pub fn new() -> Self {
+        Options::default()
+    }
+
+    pub fn big_endian(mut self) -> Self {
+        self.order = Some(Endian::Big);
+        self
+    }
+
+    pub fn little_endian(mut self) -> Self {
+        self.order = Some(Endian::Little);
+        self
+    }
+
+    pub fn msb_first(mut self) -> Self {
+        self.bit_order = Some(BitOrder::Msb);
+        self
+    }
+
+    pub fn lsb_first(mut self) -> Self {
+        self.bit_order = Some(BitOrder::Lsb);
+        self
+    }
+
+    pub fn magic(mut self, bytes: &'static [u8]) -> Self {
+        self.magic = Some(bytes);
+        self
+    }
+
+    pub fn ordered(mut self) -> Self {
+        self.choice = Choice::Ordered;
+        self
+    }
+
+    pub fn longest(mut self) -> Self {
+        self.choice = Choice::Longest;
+        self
+    }
+
+    pub fn strict(mut self) -> Self {
+        self.choice = Choice::Strict;
+        self
+    }
+
+    pub fn whitespace(mut self) -> Self {
+        self.skipper.get_or_insert_with(Skipper::default).whitespace = true;
+        self
+    }
+
+    pub fn line_comment(mut self, prefix: &'static [u8]) -> Self {
+        self.skipper
+            .get_or_insert_with(Skipper::default)
+            .line_comment = Some(prefix);
+        self
+    }
+
+    pub fn block_comment(mut self, open: &'static [u8], close: &'static [u8]) -> Self {
+        self.skipper
+            .get_or_insert_with(Skipper::default)
+            .block_comment = Some((open, close));
+        self
+    }
+
+    /// Skip nothing, rather than what the enclosing struct skips.
+    pub fn no_skip(mut self) -> Self {
+        self.skipper = Some(Skipper::default());
+        self
+    }
+
+    /// Run the parser of the fields, `parse`, with these options applied. `name` is the type
+    /// parsed, for the path of a wrong magic.
+    pub fn parse<'a, S: Source<'a>, T>(
+        &self,
+        name: &'static str,
+        input: &mut Input<S>,
+        parse: impl FnOnce(&mut Input<S>) -> Result<T, ParseError>,
+    ) -> Result<T, ParseError> {
+        if let Some(magic) = self.magic {
+            parse_tag(input, magic).map_err(|e| e.in_type(name))?;
+        }
+        input.set_choice(self.choice);
+        let parse = |input: &mut Input<S>| match self.skipper {
+            Some(skipper) => input.with_skipper(skipper, parse),
+            None => parse(input),
+        };
+        let parse = |input: &mut Input<S>| match self.bit_order {
+            Some(bit_order) => input.with_bit_order(bit_order, parse),
+            None => parse(input),
+        };
+        match self.order {
+            Some(order) => input.with_order(order, parse),
+            None => parse(input),
+        }
+    }
+
+    /// Emit the fields, `value`, with these options applied, see `Emit::emit_in_struct`.
+    /// `order` is the byte order of the enclosing struct.
+    pub fn emit_in_struct<T: Emit>(
+        &self,
+        value: &T,
+        bytes: &mut Output,
+        start: Option<usize>,
+        order: Endian,
+        redaction: Redaction,
+    ) -> Result<(), Error> {
+        if let Some(magic) = self.magic {
+            magic.emit(bytes)?;
+        }
+        let order = self.order.unwrap_or(order);
+        match self.bit_order {
+            Some(bit_order) => bytes.with_bit_order(bit_order, |bytes| {
+                value.emit_in_struct(bytes, start, order, redaction)
+            }),
+            None => value.emit_in_struct(bytes, start, order, redaction),
+        }
+    }
+}
+
+/// Build `Options` from the options of `parsable!` and `emit!`.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! parser_options {
+    (@[$($acc:tt)*]) => {
+        $($acc)*
+    };
+    (@[$($acc:tt)*] $option:ident $(($($arg:tt)*))? $(, $($rest:tt)*)?) => {
+        $crate::parser_options!(@[$($acc)* .$option($($($arg)*)?)] $($($rest)*)?)
+    };
+    ($($options:tt)*) => {
+        $crate::parser_options!(@[$crate::Options::new()] $($options)*)
+    };
+}
@@ -0,0 +1,117 @@
+use crate::bits::Unaligned;
+use crate::BitOrder;
+use failure::Fail;
+use std::io;
+use std::ops::{Deref, DerefMut};
+
+/// The bytes emitted so far, the counterpart of `Input` for `Emit::emit_in_struct`.
+///
+/// Dereferences to the bytes written. Bytes are written with `io::Write`, which fails in the
+/// middle of a byte started by bit fields, see `Output::write_bits`.
+#[derive(Debug, Default)]
+pub struct Output {
+    bytes: Vec<u8>,
+    bit_order: BitOrder,
+    /// Bits of the last byte that bit fields have not written yet.
+    bits_left: u32,
+}
+
+/// A position in an `Output` to go back to with `Output::rewind`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OutputMark {
+    len: usize,
+    bits_left: u32,
+}
+
+impl Output {
+    pub fn new() -> Self {
+        Output::default()
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.bytes
+    }
+
+    /// Order of the bits of bit fields, set by the innermost struct with a bit order option.
+    pub fn bit_order(&self) -> BitOrder {
+        self.bit_order
+    }
+
+    /// Run `f` with `bit_order`, restoring the previous one afterwards.
+    pub fn with_bit_order<T>(&mut self, bit_order: BitOrder, f: impl FnOnce(&mut Self) -> T) -> T {
+        let previous = std::mem::replace(&mut self.bit_order, bit_order);
+        let result = f(self);
+        self.bit_order = previous;
+        result
+    }
+
+    pub fn mark(&self) -> OutputMark {
+        OutputMark {
+            len: self.bytes.len(),
+            bits_left: self.bits_left,
+        }
+    }
+
+    /// Drop what was written since `mark`.
+    pub fn rewind(&mut self, mark: OutputMark) {
+        self.bytes.truncate(mark.len);
+        self.bits_left = mark.bits_left;
+        if let Some(last) = self.bytes.last_mut() {
+            *last &= !unwritten_bits(self.bit_order, mark.bits_left);
+        }
+    }
+
+    /// Set what was written since `mark` to zeros, keeping its length.
+    pub(crate) fn mask(&mut self, mark: OutputMark) {
+        if mark.bits_left > 0 {
+            self.bytes[mark.len - 1] &= !unwritten_bits(self.bit_order, mark.bits_left);
+        }
+        self.bytes[mark.len..].iter_mut().for_each(|byte| *byte = 0);
+    }
+
+    pub(crate) fn bits_left(&self) -> u32 {
+        self.bits_left
+    }
+
+    pub(crate) fn set_bits_left(&mut self, bits_left: u32) {
+        self.bits_left = bits_left;
+    }
+}
+
+/// The last `left` bits of a byte to be written in `order`.
+fn unwritten_bits(order: BitOrder, left: u32) -> u8 {
+    let low = ((1u16 << left) - 1) as u8;
+    match order {
+        BitOrder::Msb => low,
+        BitOrder::Lsb => low.reverse_bits(),
+    }
+}
+
+impl Deref for Output {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.bytes
+    }
+}
+
+impl DerefMut for Output {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        &mut self.bytes
+    }
+}
+
+impl io::Write for Output {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.bits_left > 0 && !buf.is_empty() {
+            let error = Unaligned(self.bits_left).compat();
+            return Err(io::Error::new(io::ErrorKind::InvalidData, error));
+        }
+        self.bytes.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
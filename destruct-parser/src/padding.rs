@@ -0,0 +1,280 @@
+//! Fields that take up bytes without holding data: padding, alignment and reserved bytes.
+//!
+//! ```ignore
+//! #[derive(Destruct)]
+//! #[destruct(parsable(little_endian), emit)]
+//! struct Section {
+//!     kind: u8,
+//!     pad: Pad<3>,
+//!     size: u32,
+//!     reserved: Reserved<4, true>,
+//!     name: LengthPrefixed<u8, Vec<u8>>,
+//!     align: Align<8>,
+//! }
+//! ```
+//!
+//! `Align<N>` skips to the next multiple of `N` bytes from the start of the enclosing struct,
+//! `Align<N, StreamStart>` from the start of the input, which for emitting is the value passed
+//! to `emit`, however deep in collections the `Align` is. All of them emit zeros.
+
+use crate::{Emit, Endian, Input, Output, Parsable, ParseError, Redaction, Source};
+use failure::{Error, Fail};
+use std::io::{self, Read};
+use std::marker::PhantomData;
+
+/// `N` bytes of any value.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Pad<const N: usize>;
+
+/// Bytes up to the next multiple of `N` from `R`, `StructStart` or `StreamStart`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Align<const N: usize, R = StructStart>(PhantomData<R>);
+
+/// `N` bytes set aside for later versions of a format, which must be zero if `MUST_BE_ZERO`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Reserved<const N: usize, const MUST_BE_ZERO: bool>;
+
+/// Align from the start of the enclosing struct.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct StructStart;
+
+/// Align from the start of the input.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct StreamStart;
+
+/// Where `Align` counts from.
+pub trait AlignFrom {
+    /// Whether to count from the start of the enclosing struct rather than of the input.
+    fn struct_relative() -> bool;
+}
+
+impl AlignFrom for StructStart {
+    fn struct_relative() -> bool {
+        true
+    }
+}
+
+impl AlignFrom for StreamStart {
+    fn struct_relative() -> bool {
+        false
+    }
+}
+
+#[derive(Debug, Fail)]
+#[fail(display = "reserved byte is {:#04x}, not zero", _0)]
+pub struct NonZeroReserved(pub u8);
+
+impl<const N: usize, R> Align<N, R> {
+    pub fn new() -> Self {
+        Align(PhantomData)
+    }
+
+    /// Bytes from `offset` to the next multiple of `N`.
+    fn padding(offset: usize) -> usize {
+        const { assert!(N > 0, "Align<N> needs N above zero") };
+        (N - offset % N) % N
+    }
+}
+
+/// Read `count` bytes, passing each chunk read to `f` with its offset.
+fn skip_bytes<'a, S: Source<'a>>(
+    input: &mut Input<S>,
+    mut count: usize,
+    mut f: impl FnMut(usize, &[u8]) -> Result<(), ParseError>,
+) -> Result<(), ParseError> {
+    let mut chunk = [0; 64];
+    while count > 0 {
+        let len = count.min(chunk.len());
+        let offset = input.offset();
+        input
+            .read_exact(&mut chunk[..len])
+            .map_err(|e| input.error(e))?;
+        f(offset, &chunk[..len])?;
+        count -= len;
+    }
+    Ok(())
+}
+
+impl<'a, const N: usize> Parsable<'a> for Pad<N> {
+    fn parse_input<S: Source<'a>>(input: &mut Input<S>) -> Result<Self, ParseError> {
+        skip_bytes(input, N, |_, _| Ok(()))?;
+        Ok(Pad)
+    }
+}
+
+impl<'a, const N: usize, R: AlignFrom> Parsable<'a> for Align<N, R> {
+    fn parse_input<S: Source<'a>>(input: &mut Input<S>) -> Result<Self, ParseError> {
+        let offset = input.offset();
+        let start = match input.dependencies().struct_start() {
+            Some(start) if R::struct_relative() => start,
+            _ if R::struct_relative() => offset,
+            _ => 0,
+        };
+        skip_bytes(input, Self::padding(offset - start), |_, _| Ok(()))?;
+        Ok(Align::new())
+    }
+}
+
+impl<'a, const N: usize, const MUST_BE_ZERO: bool> Parsable<'a> for Reserved<N, MUST_BE_ZERO> {
+    fn parse_input<S: Source<'a>>(input: &mut Input<S>) -> Result<Self, ParseError> {
+        skip_bytes(input, N, |offset, chunk| {
+            match chunk.iter().position(|byte| *byte != 0) {
+                Some(i) if MUST_BE_ZERO => {
+                    Err(ParseError::new(offset + i, NonZeroReserved(chunk[i])))
+                }
+                _ => Ok(()),
+            }
+        })?;
+        Ok(Reserved)
+    }
+}
+
+/// Write `count` zeros.
+fn emit_zeros<W: io::Write>(write: &mut W, count: usize) -> Result<(), Error> {
+    io::copy(&mut io::repeat(0).take(count as u64), write)?;
+    Ok(())
+}
+
+impl<const N: usize> Emit for Pad<N> {
+    fn emit<W: io::Write>(&self, write: &mut W) -> Result<(), Error> {
+        emit_zeros(write, N)
+    }
+}
+
+/// On its own, at the start of the stream and of no struct, emits nothing.
+impl<const N: usize, R: AlignFrom> Emit for Align<N, R> {
+    fn emit<W: io::Write>(&self, _: &mut W) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn emit_in_struct(
+        &self,
+        bytes: &mut Output,
+        start: Option<usize>,
+        _: Endian,
+        _: Redaction,
+    ) -> Result<(), Error> {
+        let start = match start {
+            Some(start) if R::struct_relative() => start,
+            _ if R::struct_relative() => bytes.len(),
+            _ => 0,
+        };
+        let padding = Self::padding(bytes.len() - start);
+        emit_zeros(bytes, padding)
+    }
+}
+
+impl<const N: usize, const MUST_BE_ZERO: bool> Emit for Reserved<N, MUST_BE_ZERO> {
+    fn emit<W: io::Write>(&self, write: &mut W) -> Result<(), Error> {
+        emit_zeros(write, N)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    #[derive(Destruct, Debug, PartialEq, Eq)]
+    #[destruct(parsable(little_endian), emit)]
+    struct Section {
+        kind: u8,
+        pad: Pad<3>,
+        size: u32,
+        reserved: Reserved<2, true>,
+        name: LengthPrefixed<u8, Vec<u8>>,
+        align: Align<4>,
+    }
+
+    #[derive(Destruct, Debug, PartialEq, Eq)]
+    #[destruct(parsable, emit)]
+    struct Entry {
+        tag: u8,
+        to_struct: Align<4>,
+        value: u8,
+        to_stream: Align<4, StreamStart>,
+        flags: Reserved<1, false>,
+    }
+
+    #[derive(Destruct, Debug, PartialEq, Eq)]
+    #[destruct(parsable, emit)]
+    struct Table {
+        len: u8,
+        entry: Entry,
+    }
+
+    #[derive(Destruct, Debug, PartialEq, Eq)]
+    #[destruct(parsable, emit)]
+    struct Row {
+        tag: u8,
+        to_stream: Align<4, StreamStart>,
+    }
+
+    #[derive(Destruct, Debug, PartialEq, Eq)]
+    #[destruct(parsable, emit)]
+    struct Rows {
+        first: u8,
+        rows: LengthPrefixed<u8, Vec<Row>>,
+        sized: LengthPrefixed<u8, Row, Bytes>,
+    }
+
+    #[test]
+    fn test_align_stream_nested() {
+        let row = |tag| Row {
+            tag,
+            to_stream: Align::new(),
+        };
+        let rows = Rows {
+            first: 1,
+            rows: LengthPrefixed::new(vec![row(5), row(6)]),
+            sized: LengthPrefixed::new(row(7)),
+        };
+        // Rows pad from the start of `Rows`, which starts the stream; `sized` counts its padding.
+        let bytes = [1, 2, 5, 0, 6, 0, 0, 0, 3, 7, 0, 0];
+        assert_eq!(to_bytes(&rows).unwrap(), bytes);
+        assert_eq!(Rows::parse_slice(&mut &bytes[..]).unwrap(), rows);
+        assert_eq!(roundtrip(&rows).unwrap(), rows);
+        // On its own, a collection starts the stream.
+        assert_eq!(
+            to_bytes(&vec![row(5), row(6)]).unwrap(),
+            [5, 0, 0, 0, 6, 0, 0, 0]
+        );
+    }
+
+    #[test]
+    fn test_padding() {
+        let bytes = b"\x01\xff\xff\xff\x10\x00\x00\x00\x00\x00\x03abc\x00\x00";
+        let section = Section::parse_slice(&mut &bytes[..]).unwrap();
+        assert_eq!(section.size, 16);
+        assert_eq!(section.name.value, b"abc");
+        assert_eq!(
+            to_bytes(&section).unwrap(),
+            b"\x01\x00\x00\x00\x10\x00\x00\x00\x00\x00\x03abc\x00\x00"
+        );
+        assert_eq!(roundtrip(&section).unwrap(), section);
+
+        // `to_struct` pads `Entry` from offset 1, `to_stream` pads the input from offset 6.
+        let bytes = [1, 7, 0, 0, 0, 9, 0, 0, 0xff];
+        let table = Table::parse_slice(&mut &bytes[..]).unwrap();
+        assert_eq!((table.entry.tag, table.entry.value), (7, 9));
+        let mut emitted = bytes;
+        emitted[8] = 0;
+        assert_eq!(to_bytes(&table).unwrap(), emitted);
+        assert_eq!(to_bytes(&table.entry).unwrap(), [7, 0, 0, 0, 9, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn test_reserved_errors() {
+        let bytes = b"\x01\x00\x00\x00\x10\x00\x00\x00\x00\x02\x00";
+        let error = Section::parse_slice(&mut &bytes[..]).unwrap_err();
+        let error = error.downcast::<ParseError>().unwrap();
+        assert_eq!(error.path(), "Section.reserved");
+        assert_eq!(
+            error.to_string(),
+            "Section.reserved at offset 9: reserved byte is 0x02, not zero"
+        );
+
+        let error = Pad::<4>::parse_slice(&mut &[0, 0][..]).unwrap_err();
+        let error = error.downcast::<ParseError>().unwrap();
+        assert_eq!(error.offset(), 2);
+    }
+}
@@ -0,0 +1,387 @@
+//! Repetitions that stop at the first element that does not parse.
+//!
+//! ```ignore
+//! #[derive(Destruct)]
+//! #[destruct(parsable, emit)]
+//! struct Line {
+//!     indent: Repeat<Space, 0, 8>,
+//!     words: SepBy<Many1<Letter>, Comma>,
+//!     comment: Until<u8, Newline>,
+//! }
+//! ```
+//!
+//! An element that parses without consuming any input would repeat forever, so it fails with
+//! `NoProgress` instead.
+
+use crate::{emit_buffered, Emit, Endian, Input, Output, Parsable, ParseError, Redaction, Source};
+use failure::{Error, Fail};
+use std::io;
+
+/// Between `MIN` and `MAX` elements, as many as parse.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Repeat<T, const MIN: usize, const MAX: usize>(pub Vec<T>);
+
+/// At least one element, as many as parse.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Many1<T>(pub Vec<T>);
+
+/// Elements separated by `Sep`, possibly none.
+#[derive(new, Debug, Default, Clone, PartialEq, Eq)]
+pub struct SepBy<T, Sep> {
+    pub items: Vec<T>,
+    /// One less than `items`, or none without items.
+    pub separators: Vec<Sep>,
+}
+
+/// Elements up to the first `Terminator`, which is consumed.
+#[derive(new, Debug, Default, Clone, PartialEq, Eq)]
+pub struct Until<T, Terminator> {
+    pub items: Vec<T>,
+    pub terminator: Terminator,
+}
+
+#[derive(Debug, Fail)]
+#[fail(display = "repeated value parsed without consuming input")]
+pub struct NoProgress;
+
+#[derive(Debug, Fail)]
+#[fail(display = "expected {} to {} elements, got {}", min, max, actual)]
+pub struct CountOutOfRange {
+    pub min: usize,
+    pub max: usize,
+    pub actual: usize,
+}
+
+#[derive(Debug, Fail)]
+#[fail(display = "{} separators for {} items", separators, items)]
+pub struct SeparatorCount {
+    pub items: usize,
+    pub separators: usize,
+}
+
+/// Parse up to `max` `T`s. The element that stops the repetition early is rewound, and its error
+/// returned along with the elements.
+pub(crate) fn parse_repeated<'a, T: Parsable<'a>, S: Source<'a>>(
+    input: &mut Input<S>,
+    max: usize,
+) -> Result<(Vec<T>, Option<ParseError>), ParseError> {
+    let mut result = Vec::new();
+    while result.len() < max {
+        let mark = input.mark();
+        let start = mark.offset();
+        match T::parse_input(input) {
+            Ok(_) if input.offset() == start => {
+                input.rewind(mark)?;
+                return Err(input.error(NoProgress).in_index(result.len()));
+            }
+            Ok(item) => {
+                input.release(mark);
+                result.push(item);
+            }
+            Err(error) => {
+                input.rewind(mark)?;
+                let error = error.in_index(result.len());
+                return Ok((result, Some(error)));
+            }
+        }
+    }
+    Ok((result, None))
+}
+
+/// Parse at least `min` and at most `max` `T`s. Too few fail with the error of the element that
+/// did not parse.
+fn parse_range<'a, T: Parsable<'a>, S: Source<'a>>(
+    input: &mut Input<S>,
+    min: usize,
+    max: usize,
+) -> Result<Vec<T>, ParseError> {
+    match parse_repeated(input, max)? {
+        (result, Some(error)) if result.len() < min => Err(error),
+        (result, _) => Ok(result),
+    }
+}
+
+fn emit_range<T: Emit>(
+    items: &[T],
+    min: usize,
+    max: usize,
+    bytes: &mut Output,
+    start: Option<usize>,
+    order: Endian,
+    redaction: Redaction,
+) -> Result<(), Error> {
+    if items.len() < min || items.len() > max {
+        return Err(CountOutOfRange {
+            min,
+            max,
+            actual: items.len(),
+        }
+        .into());
+    }
+    for item in items {
+        item.emit_in_struct(bytes, start, order, redaction)?;
+    }
+    Ok(())
+}
+
+impl<'a, T: Parsable<'a>, const MIN: usize, const MAX: usize> Parsable<'a> for Repeat<T, MIN, MAX> {
+    const NEEDS_STRUCT_BYTES: bool = T::NEEDS_STRUCT_BYTES;
+
+    fn parse_input<S: Source<'a>>(input: &mut Input<S>) -> Result<Self, ParseError> {
+        const { assert!(MIN <= MAX, "Repeat<T, MIN, MAX> needs MIN at most MAX") };
+        parse_range(input, MIN, MAX).map(Repeat)
+    }
+}
+
+impl<'a, T: Parsable<'a>> Parsable<'a> for Many1<T> {
+    const NEEDS_STRUCT_BYTES: bool = T::NEEDS_STRUCT_BYTES;
+
+    fn parse_input<S: Source<'a>>(input: &mut Input<S>) -> Result<Self, ParseError> {
+        parse_range(input, 1, usize::MAX).map(Many1)
+    }
+}
+
+impl<'a, T: Parsable<'a>, Sep: Parsable<'a>> Parsable<'a> for SepBy<T, Sep> {
+    const NEEDS_STRUCT_BYTES: bool = T::NEEDS_STRUCT_BYTES || Sep::NEEDS_STRUCT_BYTES;
+
+    fn parse_input<S: Source<'a>>(input: &mut Input<S>) -> Result<Self, ParseError> {
+        let mut result = SepBy::new(Vec::new(), Vec::new());
+        let mark = input.mark();
+        match T::parse_input(input) {
+            Ok(item) => {
+                input.release(mark);
+                result.items.push(item);
+            }
+            Err(_) => {
+                input.rewind(mark)?;
+                return Ok(result);
+            }
+        }
+        loop {
+            let mark = input.mark();
+            let start = mark.offset();
+            let separator = match Sep::parse_input(input) {
+                Ok(separator) => separator,
+                Err(_) => {
+                    input.rewind(mark)?;
+                    return Ok(result);
+                }
+            };
+            let item = match T::parse_input(input) {
+                Ok(item) => item,
+                Err(_) => {
+                    input.rewind(mark)?;
+                    return Ok(result);
+                }
+            };
+            if input.offset() == start {
+                input.rewind(mark)?;
+                return Err(input.error(NoProgress).in_index(result.items.len()));
+            }
+            input.release(mark);
+            result.separators.push(separator);
+            result.items.push(item);
+        }
+    }
+}
+
+impl<'a, T: Parsable<'a>, Terminator: Parsable<'a>> Parsable<'a> for Until<T, Terminator> {
+    const NEEDS_STRUCT_BYTES: bool = T::NEEDS_STRUCT_BYTES || Terminator::NEEDS_STRUCT_BYTES;
+
+    fn parse_input<S: Source<'a>>(input: &mut Input<S>) -> Result<Self, ParseError> {
+        let mut items = Vec::new();
+        loop {
+            let mark = input.mark();
+            match Terminator::parse_input(input) {
+                Ok(terminator) => {
+                    input.release(mark);
+                    return Ok(Until::new(items, terminator));
+                }
+                Err(_) => input.rewind(mark)?,
+            }
+            let start = input.offset();
+            let item = T::parse_input(input).map_err(|e| e.in_index(items.len()))?;
+            if input.offset() == start {
+                return Err(input.error(NoProgress).in_index(items.len()));
+            }
+            items.push(item);
+        }
+    }
+}
+
+impl<T: Emit, const MIN: usize, const MAX: usize> Emit for Repeat<T, MIN, MAX> {
+    fn emit<W: io::Write>(&self, write: &mut W) -> Result<(), Error> {
+        self.emit_ordered(write, Endian::default())
+    }
+
+    fn emit_ordered<W: io::Write>(&self, write: &mut W, order: Endian) -> Result<(), Error> {
+        emit_buffered(self, write, order)
+    }
+
+    fn emit_in_struct(
+        &self,
+        bytes: &mut Output,
+        start: Option<usize>,
+        order: Endian,
+        redaction: Redaction,
+    ) -> Result<(), Error> {
+        const { assert!(MIN <= MAX, "Repeat<T, MIN, MAX> needs MIN at most MAX") };
+        emit_range(&self.0, MIN, MAX, bytes, start, order, redaction)
+    }
+}
+
+impl<T: Emit> Emit for Many1<T> {
+    fn emit<W: io::Write>(&self, write: &mut W) -> Result<(), Error> {
+        self.emit_ordered(write, Endian::default())
+    }
+
+    fn emit_ordered<W: io::Write>(&self, write: &mut W, order: Endian) -> Result<(), Error> {
+        emit_buffered(self, write, order)
+    }
+
+    fn emit_in_struct(
+        &self,
+        bytes: &mut Output,
+        start: Option<usize>,
+        order: Endian,
+        redaction: Redaction,
+    ) -> Result<(), Error> {
+        emit_range(&self.0, 1, usize::MAX, bytes, start, order, redaction)
+    }
+}
+
+impl<T: Emit, Sep: Emit> Emit for SepBy<T, Sep> {
+    fn emit<W: io::Write>(&self, write: &mut W) -> Result<(), Error> {
+        self.emit_ordered(write, Endian::default())
+    }
+
+    fn emit_ordered<W: io::Write>(&self, write: &mut W, order: Endian) -> Result<(), Error> {
+        emit_buffered(self, write, order)
+    }
+
+    fn emit_in_struct(
+        &self,
+        bytes: &mut Output,
+        start: Option<usize>,
+        order: Endian,
+        redaction: Redaction,
+    ) -> Result<(), Error> {
+        if self.separators.len() != self.items.len().saturating_sub(1) {
+            return Err(SeparatorCount {
+                items: self.items.len(),
+                separators: self.separators.len(),
+            }
+            .into());
+        }
+        for (index, item) in self.items.iter().enumerate() {
+            if index > 0 {
+                self.separators[index - 1].emit_in_struct(bytes, start, order, redaction)?;
+            }
+            item.emit_in_struct(bytes, start, order, redaction)?;
+        }
+        Ok(())
+    }
+}
+
+impl<T: Emit, Terminator: Emit> Emit for Until<T, Terminator> {
+    fn emit<W: io::Write>(&self, write: &mut W) -> Result<(), Error> {
+        self.emit_ordered(write, Endian::default())
+    }
+
+    fn emit_ordered<W: io::Write>(&self, write: &mut W, order: Endian) -> Result<(), Error> {
+        emit_buffered(self, write, order)
+    }
+
+    fn emit_in_struct(
+        &self,
+        bytes: &mut Output,
+        start: Option<usize>,
+        order: Endian,
+        redaction: Redaction,
+    ) -> Result<(), Error> {
+        self.items.emit_in_struct(bytes, start, order, redaction)?;
+        self.terminator
+            .emit_in_struct(bytes, start, order, redaction)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    type Digit = Validated<u8, IsAsciiDigit>;
+    type Letter = Validated<u8, IsAsciiLowerCase>;
+    type Comma = Validated<u8, IsComma>;
+
+    #[derive(Debug, PartialEq, Eq)]
+    struct IsComma;
+
+    impl Validator<u8> for IsComma {
+        fn validate(value: &u8) -> bool {
+            *value == b','
+        }
+        fn description() -> String {
+            "IsComma".to_string()
+        }
+    }
+
+    #[derive(Destruct, Debug, PartialEq, Eq)]
+    #[destruct(parsable, emit)]
+    struct Line {
+        digits: Repeat<Digit, 1, 3>,
+        words: SepBy<Many1<Letter>, Comma>,
+        rest: Until<u8, Digit>,
+    }
+
+    #[derive(Destruct, Debug, PartialEq, Eq)]
+    #[destruct(parsable)]
+    struct Nested {
+        items: Vec<Vec<Digit>>,
+    }
+
+    fn letters(word: &[u8]) -> Many1<Letter> {
+        Many1(word.iter().map(|b| Validated::new(*b)).collect())
+    }
+
+    #[test]
+    fn test_repeat() {
+        let bytes = b"123ab,c,d;x9";
+        let line = Line::parse_slice(&mut &bytes[..]).unwrap();
+        let digits: Vec<Digit> = b"123".iter().map(|b| Validated::new(*b)).collect();
+        assert_eq!(line.digits, Repeat(digits));
+        let words = vec![letters(b"ab"), letters(b"c"), letters(b"d")];
+        assert_eq!(line.words.items, words);
+        assert_eq!(
+            line.rest,
+            Until::new(vec![b';', b'x'], Validated::new(b'9'))
+        );
+        assert_eq!(to_bytes(&line).unwrap(), bytes);
+        assert_eq!(roundtrip(&line).unwrap(), line);
+
+        let mut bytes = &b"1234"[..];
+        let digits = Repeat::<Digit, 1, 3>::parse_slice(&mut bytes).unwrap();
+        assert_eq!(digits.0.len(), 3);
+        assert_eq!(bytes, b"4");
+    }
+
+    #[test]
+    fn test_repeat_errors() {
+        let error = Line::parse_slice(&mut &b"a"[..]).unwrap_err();
+        let error = error.downcast::<ParseError>().unwrap();
+        assert_eq!(error.path(), "Line.digits[0]");
+
+        let error = Line::parse_slice(&mut &b"1ab,"[..]).unwrap_err();
+        let error = error.downcast::<ParseError>().unwrap();
+        assert_eq!(error.path(), "Line.rest[1]");
+
+        let error = Nested::parse_slice(&mut &b"x"[..]).unwrap_err();
+        let error = error.downcast::<ParseError>().unwrap();
+        assert_eq!(error.path(), "Nested.items[0]");
+        assert!(error.cause().downcast_ref::<NoProgress>().is_some());
+
+        let digits = Repeat::<Digit, 1, 3>(Vec::new());
+        assert!(to_bytes(&digits).is_err());
+        let words = SepBy::<u8, u8>::new(vec![1, 2], Vec::new());
+        assert!(to_bytes(&words).is_err());
+    }
+}
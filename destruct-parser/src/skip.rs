@@ -0,0 +1,211 @@
+//! Insignificant whitespace and comments between the tokens of text grammars.
+//!
+//! ```ignore
+//! #[derive(Destruct)]
+//! #[destruct(parsable(whitespace, line_comment(b"#")))]
+//! struct Setting {
+//!     name: Many1<Letter>,
+//!     equals: Literal<b'='>,
+//!     value: Many1<Digit>,
+//! }
+//!
+//! #[derive(Destruct)]
+//! #[destruct(parsable(whitespace, line_comment(b"#")))]
+//! struct Config {
+//!     settings: Vec<Lexeme<Setting>>,
+//! }
+//! ```
+//!
+//! A struct with a skipper skips before each of its fields, and so do the structs nested in it
+//! unless they set their own, like `no_skip` for a struct making up a single token. `Lexeme<T>`
+//! skips before `T`, for the elements of a `Vec`.
+
+use crate::{Emit, Endian, Input, Output, Parsable, ParseError, Redaction, Source};
+use byteorder::ReadBytesExt;
+use failure::{Error, Fail};
+use std::io;
+
+/// What `Input::skip_ignored` skips, set with the `whitespace`, `line_comment`, `block_comment`
+/// and `no_skip` options. Skips nothing by default.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Skipper {
+    /// ASCII whitespace.
+    pub whitespace: bool,
+    /// Comments from this prefix to the end of the line.
+    pub line_comment: Option<&'static [u8]>,
+    /// Comments between these delimiters.
+    pub block_comment: Option<(&'static [u8], &'static [u8])>,
+}
+
+/// `T`, after what the skipper of the input skips, or after whitespace without one.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Lexeme<T>(pub T);
+
+#[derive(Debug, Fail)]
+#[fail(display = "block comment is not closed")]
+pub struct UnterminatedComment;
+
+impl<'a, S: Source<'a>> Input<S> {
+    /// Skip what the skipper of the input skips, in any order and as often as it occurs.
+    pub fn skip_ignored(&mut self) -> Result<(), ParseError> {
+        let skipper = self.skipper();
+        loop {
+            let start = self.offset();
+            if skipper.whitespace {
+                while self.eat_byte(|b| b.is_ascii_whitespace())? {}
+            }
+            if let Some(prefix) = skipper.line_comment {
+                if self.eat(prefix)? {
+                    while !self.eat_byte(|b| b == b'\n')? && self.eat_byte(|_| true)? {}
+                }
+            }
+            if let Some((open, close)) = skipper.block_comment {
+                if self.eat(open)? {
+                    while !self.eat(close)? {
+                        if !self.eat_byte(|_| true)? {
+                            return Err(self.error(UnterminatedComment));
+                        }
+                    }
+                }
+            }
+            if self.offset() == start {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Read the next byte if there is one and `f` accepts it.
+    fn eat_byte(&mut self, f: impl FnOnce(u8) -> bool) -> Result<bool, ParseError> {
+        let mark = self.mark();
+        match self.read_u8() {
+            Ok(byte) if f(byte) => {
+                self.release(mark);
+                Ok(true)
+            }
+            _ => {
+                self.rewind(mark)?;
+                Ok(false)
+            }
+        }
+    }
+
+    /// Read `bytes` if the input continues with them.
+    fn eat(&mut self, bytes: &[u8]) -> Result<bool, ParseError> {
+        let mark = self.mark();
+        for expected in bytes {
+            match self.read_u8() {
+                Ok(byte) if byte == *expected => {}
+                _ => {
+                    self.rewind(mark)?;
+                    return Ok(false);
+                }
+            }
+        }
+        self.release(mark);
+        Ok(true)
+    }
+}
+
+impl<'a, T: Parsable<'a>> Parsable<'a> for Lexeme<T> {
+    const NEEDS_STRUCT_BYTES: bool = T::NEEDS_STRUCT_BYTES;
+
+    fn parse_input<S: Source<'a>>(input: &mut Input<S>) -> Result<Self, ParseError> {
+        if input.skipper() == Skipper::default() {
+            let whitespace = Skipper {
+                whitespace: true,
+                ..Skipper::default()
+            };
+            input.with_skipper(whitespace, Input::skip_ignored)?;
+        } else {
+            input.skip_ignored()?;
+        }
+        T::parse_input(input).map(Lexeme)
+    }
+
+    fn number(&self) -> Option<i128> {
+        self.0.number()
+    }
+}
+
+/// Emits `T` alone, skipped bytes are not part of the value.
+impl<T: Emit> Emit for Lexeme<T> {
+    fn emit<W: io::Write>(&self, write: &mut W) -> Result<(), Error> {
+        self.0.emit(write)
+    }
+
+    fn emit_ordered<W: io::Write>(&self, write: &mut W, order: Endian) -> Result<(), Error> {
+        self.0.emit_ordered(write, order)
+    }
+
+    fn emit_in_struct(
+        &self,
+        bytes: &mut Output,
+        start: Option<usize>,
+        order: Endian,
+        redaction: Redaction,
+    ) -> Result<(), Error> {
+        self.0.emit_in_struct(bytes, start, order, redaction)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    type Letter = Validated<u8, IsAsciiLowerCase>;
+    type Digit = Validated<u8, IsAsciiDigit>;
+
+    #[derive(Destruct, Debug, PartialEq, Eq)]
+    #[destruct(parsable(no_skip))]
+    struct Number {
+        sign: Option<Literal<b'-'>>,
+        digits: Many1<Digit>,
+    }
+
+    #[derive(Destruct, Debug, PartialEq, Eq)]
+    #[destruct(parsable)]
+    struct Setting {
+        name: Many1<Letter>,
+        equals: Literal<b'='>,
+        value: Number,
+    }
+
+    #[derive(Destruct, Debug, PartialEq, Eq)]
+    #[destruct(parsable(whitespace, line_comment(b"#"), block_comment(b"/*", b"*/")))]
+    struct Config {
+        settings: Vec<Lexeme<Setting>>,
+    }
+
+    #[derive(Destruct, Debug, PartialEq, Eq)]
+    #[destruct(parsable(block_comment(b"/*", b"*/")))]
+    struct Pair {
+        first: Letter,
+        second: Letter,
+    }
+
+    #[test]
+    fn test_skip() {
+        let mut bytes = &b"  # comment\n ab = -12 /* x */cd=3\n# end"[..];
+        let config = Config::parse_slice(&mut bytes).unwrap();
+        assert_eq!(config.settings.len(), 2);
+        assert_eq!(config.settings[0].0.value.digits.0.len(), 2);
+        assert_eq!(bytes, b"\n# end");
+
+        // `Number` is a single token.
+        let config = Config::parse_slice(&mut &b"ab = - 12"[..]).unwrap();
+        assert!(config.settings.is_empty());
+
+        let pair = Pair::parse_slice(&mut &b"a/* x */b"[..]).unwrap();
+        assert_eq!(pair.second, Validated::new(b'b'));
+        let error = Pair::parse_slice(&mut &b"a/* x"[..]).unwrap_err();
+        let error = error.downcast::<ParseError>().unwrap();
+        assert_eq!(error.path(), "Pair.second");
+        assert!(error
+            .cause()
+            .downcast_ref::<UnterminatedComment>()
+            .is_some());
+
+        let words = Vec::<Lexeme<Letter>>::parse_slice(&mut &b" a\tb c"[..]).unwrap();
+        assert_eq!(words.len(), 3);
+    }
+}
@@ -0,0 +1,252 @@
+use std::io;
+
+/// A reader that can go back to an earlier offset, so parsers can try alternatives.
+///
+/// Offsets count the bytes read through the source since it was created. `Input` calls `mark`
+/// before trying something, then either `rewind` to the marked offset and `release`, or only
+/// `release` once it is sure it will not come back. Marks are released in reverse order.
+///
+/// Sources holding the whole input in memory for `'a` also lend it out, for types that borrow
+/// from the input instead of copying.
+pub trait Source<'a>: io::Read {
+    /// Keep the bytes from `offset` on available until the matching `release`.
+    fn mark(&mut self, _offset: usize) {}
+
+    fn release(&mut self, _offset: usize) {}
+
+    /// Continue reading at `offset`, which is marked and not released yet, or which was read
+    /// while an earlier mark was held.
+    fn rewind(&mut self, offset: usize) -> io::Result<()>;
+
+    /// The bytes not read yet, if the source can lend them.
+    fn unread(&self) -> Option<&'a [u8]> {
+        None
+    }
+
+    /// Read `len` bytes without copying them, if the source can lend them and has that many.
+    fn borrow(&mut self, _len: usize) -> Option<&'a [u8]> {
+        None
+    }
+}
+
+/// Reads from a byte slice, rewinding by moving the position.
+#[derive(Debug, Clone)]
+pub struct SliceSource<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> SliceSource<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        SliceSource { bytes, position: 0 }
+    }
+
+    /// The bytes not read yet.
+    pub fn remaining(&self) -> &'a [u8] {
+        &self.bytes[self.position..]
+    }
+}
+
+impl io::Read for SliceSource<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.remaining().read(buf)?;
+        self.position += n;
+        Ok(n)
+    }
+}
+
+impl<'a> Source<'a> for SliceSource<'a> {
+    fn rewind(&mut self, offset: usize) -> io::Result<()> {
+        self.position = offset;
+        Ok(())
+    }
+
+    fn unread(&self) -> Option<&'a [u8]> {
+        Some(self.remaining())
+    }
+
+    fn borrow(&mut self, len: usize) -> Option<&'a [u8]> {
+        let bytes = self.remaining().get(..len)?;
+        self.position += len;
+        Some(bytes)
+    }
+}
+
+/// Reads from a seekable reader such as a `File`, rewinding by seeking back.
+#[derive(Debug)]
+pub struct SeekSource<R> {
+    read: R,
+    start: u64,
+}
+
+impl<R: io::Read + io::Seek> SeekSource<R> {
+    /// Offsets start at the current position of `read`.
+    pub fn new(mut read: R) -> io::Result<Self> {
+        let start = read.stream_position()?;
+        Ok(SeekSource { read, start })
+    }
+
+    pub fn into_inner(self) -> R {
+        self.read
+    }
+}
+
+impl<R: io::Read> io::Read for SeekSource<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.read.read(buf)
+    }
+}
+
+impl<R: io::Read + io::Seek> Source<'_> for SeekSource<R> {
+    fn rewind(&mut self, offset: usize) -> io::Result<()> {
+        self.read
+            .seek(io::SeekFrom::Start(self.start + offset as u64))?;
+        Ok(())
+    }
+}
+
+/// Reads from any reader, such as a pipe, keeping the bytes read since the oldest mark in memory.
+///
+/// Bytes read and then rewound over are consumed from the underlying reader, so whatever is left
+/// after parsing should be read from here rather than from the reader.
+#[derive(Debug)]
+pub struct BufferedSource<R> {
+    read: R,
+    buffer: Vec<u8>,
+    /// Offset of `buffer[0]`.
+    start: usize,
+    position: usize,
+    marks: Vec<usize>,
+}
+
+impl<R: io::Read> BufferedSource<R> {
+    pub fn new(read: R) -> Self {
+        BufferedSource {
+            read,
+            buffer: Vec::new(),
+            start: 0,
+            position: 0,
+            marks: Vec::new(),
+        }
+    }
+
+    /// Drop the buffered bytes no mark or pending read needs anymore.
+    fn trim(&mut self) {
+        let keep = self.marks.first().copied().unwrap_or(self.position);
+        let keep = keep.min(self.position);
+        self.buffer.drain(..keep - self.start);
+        self.start = keep;
+    }
+}
+
+impl<R: io::Read> io::Read for BufferedSource<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let buffered = &self.buffer[self.position - self.start..];
+        let n = if buffered.is_empty() {
+            let n = self.read.read(buf)?;
+            if !self.marks.is_empty() {
+                self.buffer.extend_from_slice(&buf[..n]);
+            } else {
+                self.start += n;
+            }
+            n
+        } else {
+            let n = buffered.len().min(buf.len());
+            buf[..n].copy_from_slice(&buffered[..n]);
+            n
+        };
+        self.position += n;
+        if self.marks.is_empty() {
+            self.trim();
+        }
+        Ok(n)
+    }
+}
+
+impl<R: io::Read> Source<'_> for BufferedSource<R> {
+    fn mark(&mut self, offset: usize) {
+        self.marks.push(offset);
+    }
+
+    fn release(&mut self, offset: usize) {
+        debug_assert_eq!(self.marks.last(), Some(&offset));
+        self.marks.pop();
+        self.trim();
+    }
+
+    fn rewind(&mut self, offset: usize) -> io::Result<()> {
+        debug_assert!(offset >= self.start && offset <= self.start + self.buffer.len());
+        self.position = offset;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::*;
+
+    #[derive(Destruct, Debug, PartialEq, Eq)]
+    #[destruct(parsable)]
+    struct Word {
+        letters: Vec<Validated<u8, IsAsciiLowerCase>>,
+        end: Validated<u8, IsAsciiDigit>,
+    }
+
+    #[derive(Destruct, Debug, PartialEq)]
+    #[destruct(parsable)]
+    enum Token {
+        Number(Validated<u8, IsAsciiDigit>, Validated<u8, IsAsciiDigit>),
+        Word(Word),
+    }
+
+    /// A pipe-like reader: one byte per read, no `Clone` and no `Seek`.
+    struct Trickle<'a>(&'a [u8]);
+
+    impl io::Read for Trickle<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let n = buf.len().min(self.0.len()).min(1);
+            buf[..n].copy_from_slice(&self.0[..n]);
+            self.0 = &self.0[n..];
+            Ok(n)
+        }
+    }
+
+    fn word(letters: &[u8], end: u8) -> Token {
+        Token::Word(Word {
+            letters: letters.iter().map(|b| Validated::new(*b)).collect(),
+            end: Validated::new(end),
+        })
+    }
+
+    #[test]
+    fn test_buffered() {
+        let mut input = Input::new(BufferedSource::new(Trickle(b"1ab2")));
+        assert_eq!(Token::parse_input(&mut input).unwrap(), word(b"", b'1'));
+        assert_eq!(Token::parse_input(&mut input).unwrap(), word(b"ab", b'2'));
+        assert_eq!(input.offset(), 4);
+        assert!(input.into_inner().buffer.is_empty());
+
+        let token = Token::parse(&mut Trickle(b"42")).unwrap();
+        assert_eq!(
+            token,
+            Token::Number(Validated::new(b'4'), Validated::new(b'2'))
+        );
+    }
+
+    #[test]
+    fn test_seek() {
+        let mut cursor = io::Cursor::new(b"xxab3".to_vec());
+        cursor.set_position(2);
+        let mut input = Input::new(SeekSource::new(cursor).unwrap());
+        assert_eq!(Token::parse_input(&mut input).unwrap(), word(b"ab", b'3'));
+        assert_eq!(input.into_inner().into_inner().position(), 5);
+    }
+
+    #[test]
+    fn test_slice() {
+        let mut input = Input::new(SliceSource::new(b"ab3rest"));
+        assert_eq!(Token::parse_input(&mut input).unwrap(), word(b"ab", b'3'));
+        assert_eq!(input.source().remaining(), b"rest");
+    }
+}
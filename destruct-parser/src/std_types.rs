@@ -0,0 +1,222 @@
+//! `Option`, `Box`, tuples, `()` and `PhantomData`.
+//!
+//! ```ignore
+//! #[derive(Destruct)]
+//! #[destruct(parsable, emit)]
+//! enum Expr {
+//!     Num(Digit),
+//!     Add(Literal<b'('>, Box<Expr>, Literal<b'+'>, Box<Expr>, Literal<b')'>),
+//! }
+//! ```
+//!
+//! A recursive type must consume input before recursing, or parsing it never ends.
+
+use crate::{emit_buffered, Emit, Endian, Input, Output, Parsable, ParseError, Redaction, Source};
+use failure::Error;
+use std::io;
+use std::marker::PhantomData;
+
+/// `Some` if `T` parses, otherwise `None` with nothing consumed.
+impl<'a, T: Parsable<'a>> Parsable<'a> for Option<T> {
+    const NEEDS_STRUCT_BYTES: bool = T::NEEDS_STRUCT_BYTES;
+
+    fn parse_input<S: Source<'a>>(input: &mut Input<S>) -> Result<Self, ParseError> {
+        let mark = input.mark();
+        match T::parse_input(input) {
+            Ok(value) => {
+                input.release(mark);
+                Ok(Some(value))
+            }
+            Err(_) => {
+                input.rewind(mark)?;
+                Ok(None)
+            }
+        }
+    }
+}
+
+impl<'a, T: Parsable<'a>> Parsable<'a> for Box<T> {
+    const NEEDS_STRUCT_BYTES: bool = T::NEEDS_STRUCT_BYTES;
+
+    fn parse_input<S: Source<'a>>(input: &mut Input<S>) -> Result<Self, ParseError> {
+        T::parse_input(input).map(Box::new)
+    }
+}
+
+impl<'a> Parsable<'a> for () {
+    fn parse_input<S: Source<'a>>(_: &mut Input<S>) -> Result<Self, ParseError> {
+        Ok(())
+    }
+}
+
+impl<'a, T> Parsable<'a> for PhantomData<T> {
+    fn parse_input<S: Source<'a>>(_: &mut Input<S>) -> Result<Self, ParseError> {
+        Ok(PhantomData)
+    }
+}
+
+impl<T: Emit> Emit for Option<T> {
+    fn emit<W: io::Write>(&self, write: &mut W) -> Result<(), Error> {
+        self.emit_ordered(write, Endian::default())
+    }
+
+    fn emit_ordered<W: io::Write>(&self, write: &mut W, order: Endian) -> Result<(), Error> {
+        match self {
+            Some(value) => value.emit_ordered(write, order),
+            None => Ok(()),
+        }
+    }
+
+    fn emit_in_struct(
+        &self,
+        bytes: &mut Output,
+        start: Option<usize>,
+        order: Endian,
+        redaction: Redaction,
+    ) -> Result<(), Error> {
+        match self {
+            Some(value) => value.emit_in_struct(bytes, start, order, redaction),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<T: Emit + ?Sized> Emit for Box<T> {
+    fn emit<W: io::Write>(&self, write: &mut W) -> Result<(), Error> {
+        (**self).emit(write)
+    }
+
+    fn emit_ordered<W: io::Write>(&self, write: &mut W, order: Endian) -> Result<(), Error> {
+        (**self).emit_ordered(write, order)
+    }
+
+    fn emit_in_struct(
+        &self,
+        bytes: &mut Output,
+        start: Option<usize>,
+        order: Endian,
+        redaction: Redaction,
+    ) -> Result<(), Error> {
+        (**self).emit_in_struct(bytes, start, order, redaction)
+    }
+}
+
+impl Emit for () {
+    fn emit<W: io::Write>(&self, _: &mut W) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+impl<T> Emit for PhantomData<T> {
+    fn emit<W: io::Write>(&self, _: &mut W) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+/// Tuples parse their elements in order, failing at the path `.0`, `.1`, ... like tuple structs.
+macro_rules! tuple {
+    ($(($($t:ident $index:tt),+))*) => {
+        $(
+            impl<'a, $($t: Parsable<'a>),+> Parsable<'a> for ($($t,)+) {
+                const NEEDS_STRUCT_BYTES: bool = false $(|| $t::NEEDS_STRUCT_BYTES)+;
+
+                fn parse_input<S: Source<'a>>(input: &mut Input<S>) -> Result<Self, ParseError> {
+                    Ok(($($t::parse_input(input).map_err(|e| e.in_position($index))?,)+))
+                }
+            }
+
+            impl<$($t: Emit),+> Emit for ($($t,)+) {
+                fn emit<W: io::Write>(&self, write: &mut W) -> Result<(), Error> {
+                    self.emit_ordered(write, Endian::default())
+                }
+
+                fn emit_ordered<W: io::Write>(&self, write: &mut W, order: Endian) -> Result<(), Error> {
+                    emit_buffered(self, write, order)
+                }
+
+                fn emit_in_struct(
+                    &self,
+                    bytes: &mut Output,
+                    start: Option<usize>,
+                    order: Endian,
+                    redaction: Redaction,
+                ) -> Result<(), Error> {
+                    $(self.$index.emit_in_struct(bytes, start, order, redaction)?;)+
+                    Ok(())
+                }
+            }
+        )*
+    };
+}
+
+tuple! {
+    (A 0)
+    (A 0, B 1)
+    (A 0, B 1, C 2)
+    (A 0, B 1, C 2, D 3)
+    (A 0, B 1, C 2, D 3, E 4)
+    (A 0, B 1, C 2, D 3, E 4, F 5)
+    (A 0, B 1, C 2, D 3, E 4, F 5, G 6)
+    (A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+    use std::marker::PhantomData;
+
+    type Digit = Validated<u8, IsAsciiDigit>;
+
+    #[derive(Destruct, Debug, PartialEq, Eq)]
+    #[destruct(parsable, emit)]
+    enum Expr {
+        Num(Digit),
+        Add(
+            Literal<b'('>,
+            Box<Expr>,
+            Literal<b'+'>,
+            Box<Expr>,
+            Literal<b')'>,
+        ),
+    }
+
+    #[derive(Destruct, Debug, PartialEq, Eq)]
+    #[destruct(parsable, emit)]
+    struct Statement {
+        expr: Expr,
+        range: Option<(Literal<b':'>, Digit, Digit)>,
+        end: (),
+        marker: PhantomData<String>,
+    }
+
+    fn add(left: Expr, right: Expr) -> Expr {
+        Expr::Add(Literal, Box::new(left), Literal, Box::new(right), Literal)
+    }
+
+    fn num(digit: u8) -> Expr {
+        Expr::Num(Validated::new(digit))
+    }
+
+    #[test]
+    fn test_recursive() {
+        let bytes = b"(1+(2+3))";
+        let expr = Expr::parse_slice(&mut &bytes[..]).unwrap();
+        assert_eq!(expr, add(num(b'1'), add(num(b'2'), num(b'3'))));
+        assert_eq!(to_bytes(&expr).unwrap(), bytes);
+    }
+
+    #[test]
+    fn test_optional() {
+        let statement = Statement::parse_slice(&mut &b"(1+2):3"[..]).unwrap();
+        assert_eq!(statement.range, None);
+
+        let statement = Statement::parse_slice(&mut &b"4:56"[..]).unwrap();
+        let range = (Literal, Validated::new(b'5'), Validated::new(b'6'));
+        assert_eq!(statement.range, Some(range));
+        assert_eq!(roundtrip(&statement).unwrap(), statement);
+
+        let error = <(u8, Digit)>::parse_slice(&mut &b"1x"[..]).unwrap_err();
+        let error = error.downcast::<ParseError>().unwrap();
+        assert_eq!(error.path(), ".1");
+    }
+}
@@ -0,0 +1,198 @@
+//! Fixed bytes that must be present but carry no value, such as signatures and keywords.
+//!
+//! ```ignore
+//! define_tag!(Png, b"\x89PNG\r\n\x1a\n");
+//!
+//! #[derive(Destruct)]
+//! #[destruct(parsable, emit)]
+//! struct Image {
+//!     signature: Tag<Png>,
+//!     width: u32,
+//!     separator: Literal<b','>,
+//!     height: u32,
+//! }
+//! ```
+//!
+//! A whole struct can also require a prefix with the `magic` option, as in
+//! `#[destruct(parsable(magic(b"PK\x03\x04")), emit)]`.
+
+use crate::{Emit, Input, Parsable, ParseError, Source};
+use failure::{Error, Fail};
+use std::fmt;
+use std::io;
+use std::marker::PhantomData;
+
+/// The bytes of a `Tag`, usually implemented with `define_tag!`.
+pub trait TagBytes {
+    const BYTES: &'static [u8];
+}
+
+/// The bytes of `T`.
+pub struct Tag<T: TagBytes>(PhantomData<T>);
+
+/// The single byte `B`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Literal<const B: u8>;
+
+/// The input does not hold the expected tag. The offset of the error is the first differing byte.
+#[derive(Debug, Fail)]
+#[fail(display = "expected tag b\"{}\"", _0)]
+pub struct TagMismatch(pub Escaped);
+
+/// Bytes displayed as in a byte string literal.
+#[derive(Debug)]
+pub struct Escaped(pub &'static [u8]);
+
+impl fmt::Display for Escaped {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0.escape_ascii())
+    }
+}
+
+/// Read `bytes` from `input`.
+pub(crate) fn parse_tag<'a, S: Source<'a>>(
+    input: &mut Input<S>,
+    bytes: &'static [u8],
+) -> Result<(), ParseError> {
+    for expected in bytes {
+        let offset = input.offset();
+        if u8::parse_input(input)? != *expected {
+            return Err(ParseError::new(offset, TagMismatch(Escaped(bytes))));
+        }
+    }
+    Ok(())
+}
+
+/// Define a `TagBytes` type for `Tag`.
+///
+/// `define_tag!(Zip, b"PK\x03\x04")` defines `Zip`, for fields of type `Tag<Zip>`.
+#[macro_export]
+macro_rules! define_tag {
+    ($name:ident, $bytes:expr) => {
+        #[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+        pub struct $name;
+
+        impl $crate::TagBytes for $name {
+            const BYTES: &'static [u8] = $bytes;
+        }
+    };
+}
+
+impl<T: TagBytes> Tag<T> {
+    pub fn new() -> Self {
+        Tag(PhantomData)
+    }
+}
+
+// Implemented by hand, deriving them would require them of `T`.
+impl<T: TagBytes> Default for Tag<T> {
+    fn default() -> Self {
+        Tag::new()
+    }
+}
+
+impl<T: TagBytes> Clone for Tag<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T: TagBytes> Copy for Tag<T> {}
+
+impl<T: TagBytes> PartialEq for Tag<T> {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
+
+impl<T: TagBytes> Eq for Tag<T> {}
+
+impl<T: TagBytes> fmt::Debug for Tag<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Tag(b\"{}\")", Escaped(T::BYTES))
+    }
+}
+
+impl<'a, T: TagBytes> Parsable<'a> for Tag<T> {
+    fn parse_input<S: Source<'a>>(input: &mut Input<S>) -> Result<Self, ParseError> {
+        parse_tag(input, T::BYTES).map(|_| Tag::new())
+    }
+}
+
+impl<'a, const B: u8> Parsable<'a> for Literal<B> {
+    fn parse_input<S: Source<'a>>(input: &mut Input<S>) -> Result<Self, ParseError> {
+        parse_tag(input, &[B]).map(|_| Literal)
+    }
+}
+
+impl<T: TagBytes> Emit for Tag<T> {
+    fn emit<W: io::Write>(&self, write: &mut W) -> Result<(), Error> {
+        T::BYTES.emit(write)
+    }
+}
+
+impl<const B: u8> Emit for Literal<B> {
+    fn emit<W: io::Write>(&self, write: &mut W) -> Result<(), Error> {
+        B.emit(write)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    define_tag!(Zip, b"PK\x03\x04");
+
+    #[derive(Destruct, Debug, PartialEq, Eq)]
+    #[destruct(parsable, emit)]
+    struct Entry {
+        signature: Tag<Zip>,
+        size: u8,
+        separator: Literal<b','>,
+        name: Vec<Validated<u8, IsAsciiLowerCase>>,
+    }
+
+    #[derive(Destruct, Debug, PartialEq, Eq)]
+    #[destruct(parsable(magic(b"GIF89a")), emit)]
+    struct Gif {
+        width: u8,
+        height: u8,
+    }
+
+    #[test]
+    fn test_tag() {
+        let bytes = b"PK\x03\x04\x07,ab";
+        let entry = Entry::parse_slice(&mut &bytes[..]).unwrap();
+        assert_eq!(entry.size, 7);
+        assert_eq!(entry.name.len(), 2);
+        assert_eq!(to_bytes(&entry).unwrap(), bytes);
+
+        let error = Entry::parse_slice(&mut &b"PK\x05\x06"[..]).unwrap_err();
+        let error = error.downcast::<ParseError>().unwrap();
+        assert_eq!(error.path(), "Entry.signature");
+        assert_eq!(error.offset(), 2);
+        assert_eq!(
+            error.to_string(),
+            "Entry.signature at offset 2: expected tag b\"PK\\x03\\x04\""
+        );
+    }
+
+    #[test]
+    fn test_magic() {
+        let gif = Gif {
+            width: 2,
+            height: 3,
+        };
+        assert_eq!(to_bytes(&gif).unwrap(), b"GIF89a\x02\x03");
+        assert_eq!(roundtrip(&gif).unwrap(), gif);
+
+        let error = Gif::parse_slice(&mut &b"GIF87a\x02\x03"[..]).unwrap_err();
+        let error = error.downcast::<ParseError>().unwrap();
+        assert_eq!(error.offset(), 4);
+        assert!(error.cause().downcast_ref::<TagMismatch>().is_some());
+        assert_eq!(
+            error.to_string(),
+            "Gif at offset 4: expected tag b\"GIF89a\""
+        );
+    }
+}
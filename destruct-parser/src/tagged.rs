@@ -0,0 +1,302 @@
+//! Enums preceded by a tag selecting the variant, as in most binary protocols.
+//!
+//! ```ignore
+//! #[derive(Destruct)]
+//! #[destruct(parsable(tag(u8)), emit)]
+//! enum Message {
+//!     Ping(u32) = 1,
+//!     Data { len: u16 } = 0x10,
+//!     Close,
+//! }
+//! ```
+//!
+//! The tag is the discriminant of the variant, here `0x11` for `Close`. Unlike a plain derived
+//! enum, the variants are not tried in turn: an unknown tag fails right away, and so does a
+//! variant that does not parse.
+
+use crate::{
+    emit_buffered, BigEndian, Emit, Endian, Input, LittleEndian, Output, Parsable, ParseError,
+    Redaction, Source,
+};
+use destruct::*;
+use failure::{Error, Fail};
+use std::convert::TryFrom;
+use std::fmt;
+use std::io;
+use std::marker::PhantomData;
+
+/// A number used as the tag of a tagged enum.
+pub trait Discriminant: Sized {
+    fn to_discriminant(&self) -> i128;
+    fn from_discriminant(discriminant: i128) -> Option<Self>;
+}
+
+macro_rules! discriminant {
+    ($($t:ty),*) => {
+        $(
+            impl Discriminant for $t {
+                fn to_discriminant(&self) -> i128 {
+                    i128::from(*self)
+                }
+
+                fn from_discriminant(discriminant: i128) -> Option<Self> {
+                    <$t>::try_from(discriminant).ok()
+                }
+            }
+        )*
+    };
+}
+
+discriminant!(u8, u16, u32, u64, i8, i16, i32, i64);
+
+impl<T: Discriminant> Discriminant for BigEndian<T> {
+    fn to_discriminant(&self) -> i128 {
+        self.0.to_discriminant()
+    }
+
+    fn from_discriminant(discriminant: i128) -> Option<Self> {
+        T::from_discriminant(discriminant).map(BigEndian)
+    }
+}
+
+impl<T: Discriminant> Discriminant for LittleEndian<T> {
+    fn to_discriminant(&self) -> i128 {
+        self.0.to_discriminant()
+    }
+
+    fn from_discriminant(discriminant: i128) -> Option<Self> {
+        T::from_discriminant(discriminant).map(LittleEndian)
+    }
+}
+
+/// The destruct type `D` of an enum, preceded by a `Tag`. Used by `parsable!(T, tag(Tag))` and
+/// `emit!(T, tag(Tag))`.
+#[derive(new, Debug)]
+pub struct Tagged<Tag, D> {
+    pub value: D,
+    #[new(default)]
+    tag: PhantomData<Tag>,
+}
+
+#[derive(Debug)]
+pub struct UnknownTag {
+    pub tag: i128,
+    pub enum_name: &'static str,
+}
+
+/// A negative tag is shown in decimal, as in hex an `i128` is 32 digits of two's complement.
+impl fmt::Display for UnknownTag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.tag < 0 {
+            write!(f, "unknown tag {} for enum {}", self.tag, self.enum_name)
+        } else {
+            write!(
+                f,
+                "unknown tag {:#04x} for enum {}",
+                self.tag, self.enum_name
+            )
+        }
+    }
+}
+
+impl Fail for UnknownTag {}
+
+#[derive(Debug, Fail)]
+#[fail(display = "discriminant {} does not fit in the tag", _0)]
+pub struct DiscriminantOverflow(pub i128);
+
+/// Parsing the variant selected by a tag.
+///
+/// Implemented for `DestructEnumVariant` and `DestructEnumEnd`.
+pub trait TaggedVariants<'a>: Sized {
+    /// Parse the variant whose discriminant is `tag`, or return `None` if there is none.
+    fn parse_variant<S: Source<'a>>(
+        input: &mut Input<S>,
+        tag: i128,
+    ) -> Result<Option<Self>, ParseError>;
+}
+
+impl<'a, M: DestructEnumMetadata + 'static> TaggedVariants<'a> for DestructEnumEnd<M> {
+    fn parse_variant<S: Source<'a>>(_: &mut Input<S>, _: i128) -> Result<Option<Self>, ParseError> {
+        Ok(None)
+    }
+}
+
+impl<'a, H: Parsable<'a>, T: TaggedVariants<'a>, M: DestructEnumVariantMetadata + 'static>
+    TaggedVariants<'a> for DestructEnumVariant<H, T, M>
+{
+    fn parse_variant<S: Source<'a>>(
+        input: &mut Input<S>,
+        tag: i128,
+    ) -> Result<Option<Self>, ParseError> {
+        if M::discriminant() == tag {
+            H::parse_input(input)
+                .map(|head| Some(DestructEnumVariant::new_head(head)))
+                .map_err(|e| e.in_variant::<M>())
+        } else {
+            Ok(T::parse_variant(input, tag)?.map(DestructEnumVariant::new_tail))
+        }
+    }
+}
+
+/// The discriminant of the variant a value holds.
+///
+/// Implemented for `DestructEnumVariant` and `DestructEnumEnd`.
+pub trait ActiveVariant {
+    fn discriminant(&self) -> i128;
+}
+
+impl<M: DestructEnumMetadata + 'static> ActiveVariant for DestructEnumEnd<M> {
+    fn discriminant(&self) -> i128 {
+        unreachable!()
+    }
+}
+
+impl<H, T: ActiveVariant, M: DestructEnumVariantMetadata + 'static> ActiveVariant
+    for DestructEnumVariant<H, T, M>
+{
+    fn discriminant(&self) -> i128 {
+        match self {
+            DestructEnumVariant::Head(..) => M::discriminant(),
+            DestructEnumVariant::Tail(tail, _) => tail.discriminant(),
+        }
+    }
+}
+
+impl<
+        'a,
+        Tag: Parsable<'a> + Discriminant,
+        T: TaggedVariants<'a>,
+        M: DestructEnumMetadata + 'static,
+    > Parsable<'a> for Tagged<Tag, DestructEnumBegin<T, M>>
+{
+    fn parse_input<S: Source<'a>>(input: &mut Input<S>) -> Result<Self, ParseError> {
+        // The variant is chosen by the tag, not by a `Choice`.
+        input.take_choice();
+        let offset = input.offset();
+        let tag = Tag::parse_input(input)
+            .map_err(|e| e.in_type(M::enum_name()))?
+            .to_discriminant();
+        match T::parse_variant(input, tag)? {
+            Some(variants) => Ok(Tagged::new(DestructEnumBegin::new(variants))),
+            None => {
+                let error = UnknownTag {
+                    tag,
+                    enum_name: M::enum_name(),
+                };
+                Err(ParseError::new(offset, error).in_type(M::enum_name()))
+            }
+        }
+    }
+}
+
+impl<Tag: Emit + Discriminant, T: Emit + ActiveVariant, M: DestructEnumMetadata + 'static> Emit
+    for Tagged<Tag, DestructEnumBegin<T, M>>
+{
+    fn emit<W: io::Write>(&self, write: &mut W) -> Result<(), Error> {
+        self.emit_ordered(write, Endian::default())
+    }
+
+    fn emit_ordered<W: io::Write>(&self, write: &mut W, order: Endian) -> Result<(), Error> {
+        emit_buffered(self, write, order)
+    }
+
+    fn emit_in_struct(
+        &self,
+        bytes: &mut Output,
+        start: Option<usize>,
+        order: Endian,
+        redaction: Redaction,
+    ) -> Result<(), Error> {
+        let discriminant = self.value.variants.discriminant();
+        Tag::from_discriminant(discriminant)
+            .ok_or(DiscriminantOverflow(discriminant))?
+            .emit_ordered(bytes, order)?;
+        self.value.emit_in_struct(bytes, start, order, redaction)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    #[derive(Destruct, Debug, PartialEq, Eq)]
+    #[destruct(parsable(tag(u8)), emit)]
+    #[repr(u8)]
+    enum Message {
+        Ping(u8) = 1,
+        Data { len: u16 } = 0x10,
+        Close,
+    }
+
+    #[derive(Destruct, Debug, PartialEq, Eq)]
+    #[destruct(parsable(tag(u16), little_endian), emit)]
+    enum Command {
+        Start(u16),
+        Stop,
+    }
+
+    #[derive(Destruct, Debug, PartialEq, Eq)]
+    #[destruct(parsable(tag(i8)), emit)]
+    #[repr(i8)]
+    enum Step {
+        Back = -1,
+        Forward = 1,
+    }
+
+    #[derive(Destruct, Debug, PartialEq, Eq)]
+    #[destruct(parsable)]
+    struct Frame {
+        id: u8,
+        message: Message,
+    }
+
+    #[test]
+    fn test_tagged() {
+        let bytes = [0x10, 0x01, 0x02, 0x11, 0x01, 0x07];
+        let mut input = &bytes[..];
+        let data = Message::Data { len: 0x0102 };
+        assert_eq!(Message::parse_slice(&mut input).unwrap(), data);
+        assert_eq!(Message::parse_slice(&mut input).unwrap(), Message::Close);
+        assert_eq!(Message::parse_slice(&mut input).unwrap(), Message::Ping(7));
+        assert_eq!(to_bytes(&data).unwrap(), &bytes[..3]);
+        assert_eq!(roundtrip(&Message::Close).unwrap(), Message::Close);
+
+        let start = Command::Start(0x0203);
+        assert_eq!(to_bytes(&start).unwrap(), [0x00, 0x00, 0x03, 0x02]);
+        assert_eq!(roundtrip(&start).unwrap(), start);
+        assert_eq!(to_bytes(&Command::Stop).unwrap(), [0x01, 0x00]);
+    }
+
+    #[test]
+    fn test_tagged_errors() {
+        let error = Message::parse_slice(&mut &[0x7f, 0x00][..]).unwrap_err();
+        let error = error.downcast::<ParseError>().unwrap();
+        assert_eq!(error.path(), "Message");
+        assert_eq!(error.offset(), 0);
+        assert_eq!(
+            error.to_string(),
+            "Message at offset 0: unknown tag 0x7f for enum Message"
+        );
+
+        // The tag selects `Data`, other variants are not tried.
+        let error = Message::parse_slice(&mut &[0x10, 0x01][..]).unwrap_err();
+        let error = error.downcast::<ParseError>().unwrap();
+        assert_eq!(error.path(), "Message::Data.len");
+        let error = Frame::parse_slice(&mut &[0x02, 0x10, 0x01][..]).unwrap_err();
+        let error = error.downcast::<ParseError>().unwrap();
+        assert_eq!(error.path(), "Frame.message::Data.len");
+
+        assert_eq!(Step::parse_slice(&mut &[0xff][..]).unwrap(), Step::Back);
+        let error = Step::parse_slice(&mut &[0xfe][..]).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Step at offset 0: unknown tag -2 for enum Step"
+        );
+        let error = Step::parse_slice(&mut &[0x7f][..]).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Step at offset 0: unknown tag 0x7f for enum Step"
+        );
+    }
+}
@@ -0,0 +1,204 @@
+//! UTF-8 text: characters, strings of validated characters, identifiers and keywords.
+//!
+//! ```ignore
+//! define_tag!(Let, b"let");
+//!
+//! #[derive(Destruct)]
+//! #[destruct(parsable(whitespace))]
+//! struct Binding {
+//!     keyword: Keyword<Let>,
+//!     name: Identifier,
+//!     equals: Literal<b'='>,
+//!     value: Text<IsNumeric>,
+//! }
+//! ```
+
+use crate::tag::parse_tag;
+use crate::{
+    parse_repeated, Emit, Escaped, Input, Parsable, ParseError, Source, TagBytes, ValidateError,
+    Validated, Validator,
+};
+use failure::{Error, Fail};
+use std::io;
+use std::marker::PhantomData;
+use std::str;
+use unicode_xid::UnicodeXID;
+
+/// One or more characters accepted by `F`.
+#[derive(new, Debug, PartialEq, Eq)]
+pub struct Text<F: Validator<char> + 'static> {
+    pub value: String,
+    #[new(default)]
+    validator: PhantomData<&'static F>,
+}
+
+/// A Unicode identifier: an XID start character or `_`, then XID continue characters.
+#[derive(Debug, Default, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Identifier(pub String);
+
+/// The bytes of `K`, not followed by an XID continue character, so that `Keyword<If>` matches
+/// `if (` but not `iffy`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Keyword<K: TagBytes>(PhantomData<K>);
+
+#[derive(Debug, Fail)]
+#[fail(display = "invalid UTF-8 sequence")]
+pub struct InvalidUtf8;
+
+#[derive(Debug, Fail)]
+#[fail(display = "keyword \"{}\" continues as an identifier", _0)]
+pub struct KeywordBoundary(pub Escaped);
+
+impl<'a> Parsable<'a> for char {
+    fn parse_input<S: Source<'a>>(input: &mut Input<S>) -> Result<Self, ParseError> {
+        let start = input.offset();
+        let first = u8::parse_input(input)?;
+        let len = match first {
+            0x00..=0x7f => 1,
+            0xc0..=0xdf => 2,
+            0xe0..=0xef => 3,
+            0xf0..=0xf7 => 4,
+            _ => return Err(ParseError::new(start, InvalidUtf8)),
+        };
+        let mut bytes = [first, 0, 0, 0];
+        for byte in &mut bytes[1..len] {
+            *byte = u8::parse_input(input)?;
+        }
+        match str::from_utf8(&bytes[..len]) {
+            Ok(s) => Ok(s.chars().next().unwrap()),
+            Err(_) => Err(ParseError::new(start, InvalidUtf8)),
+        }
+    }
+}
+
+impl<'a, F: Validator<char>> Parsable<'a> for Text<F> {
+    fn parse_input<S: Source<'a>>(input: &mut Input<S>) -> Result<Self, ParseError> {
+        match parse_repeated::<Validated<char, F>, S>(input, usize::MAX)? {
+            (chars, Some(error)) if chars.is_empty() => Err(error),
+            (chars, _) => Ok(Text::new(chars.into_iter().map(|c| c.value).collect())),
+        }
+    }
+}
+
+impl<'a> Parsable<'a> for Identifier {
+    fn parse_input<S: Source<'a>>(input: &mut Input<S>) -> Result<Self, ParseError> {
+        let start = input.offset();
+        let first = char::parse_input(input)?;
+        if !(first == '_' || first.is_xid_start()) {
+            return Err(ParseError::new(
+                start,
+                ValidateError("Identifier".to_string()),
+            ));
+        }
+        let (rest, _) =
+            parse_repeated::<Validated<char, crate::IsXidContinue>, S>(input, usize::MAX)?;
+        let mut result = first.to_string();
+        result.extend(rest.into_iter().map(|c| c.value));
+        Ok(Identifier(result))
+    }
+}
+
+impl<K: TagBytes> Keyword<K> {
+    pub fn new() -> Self {
+        Keyword(PhantomData)
+    }
+}
+
+impl<'a, K: TagBytes> Parsable<'a> for Keyword<K> {
+    fn parse_input<S: Source<'a>>(input: &mut Input<S>) -> Result<Self, ParseError> {
+        parse_tag(input, K::BYTES)?;
+        let mark = input.mark();
+        let next = char::parse_input(input);
+        input.rewind(mark)?;
+        match next {
+            Ok(c) if c.is_xid_continue() => Err(input.error(KeywordBoundary(Escaped(K::BYTES)))),
+            _ => Ok(Keyword::new()),
+        }
+    }
+}
+
+impl Emit for char {
+    fn emit<W: io::Write>(&self, write: &mut W) -> Result<(), Error> {
+        self.encode_utf8(&mut [0; 4]).emit(write)
+    }
+}
+
+impl<F: Validator<char>> Emit for Text<F> {
+    /// Validate again, a `Text` built with `new` may hold anything.
+    fn emit<W: io::Write>(&self, write: &mut W) -> Result<(), Error> {
+        if self.value.is_empty() || !self.value.chars().all(|c| F::validate(&c)) {
+            return Err(ValidateError(F::description()).into());
+        }
+        self.value.emit(write)
+    }
+}
+
+impl Emit for Identifier {
+    fn emit<W: io::Write>(&self, write: &mut W) -> Result<(), Error> {
+        let mut chars = self.0.chars();
+        match chars.next() {
+            Some(first) if first == '_' || first.is_xid_start() => {}
+            _ => return Err(ValidateError("Identifier".to_string()).into()),
+        }
+        if !chars.all(UnicodeXID::is_xid_continue) {
+            return Err(ValidateError("Identifier".to_string()).into());
+        }
+        self.0.emit(write)
+    }
+}
+
+impl<K: TagBytes> Emit for Keyword<K> {
+    fn emit<W: io::Write>(&self, write: &mut W) -> Result<(), Error> {
+        K::BYTES.emit(write)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    define_tag!(Let, b"let");
+
+    #[derive(Destruct, Debug, PartialEq, Eq)]
+    #[destruct(parsable(whitespace), emit)]
+    struct Binding {
+        keyword: Keyword<Let>,
+        name: Identifier,
+        equals: Literal<b'='>,
+        value: Text<IsNumeric>,
+    }
+
+    #[test]
+    fn test_text() {
+        let binding = Binding::parse_slice(&mut "let café = ٤٢".as_bytes()).unwrap();
+        assert_eq!(binding.name, Identifier("café".to_string()));
+        assert_eq!(binding.value.value, "٤٢");
+        // Emit writes no whitespace, and `letcafé` reads back as no keyword.
+        assert_eq!(to_bytes(&binding).unwrap(), "letcafé=٤٢".as_bytes());
+        assert!(roundtrip(&binding).is_err());
+
+        let mut bytes = "é!".as_bytes();
+        assert_eq!(char::parse_slice(&mut bytes).unwrap(), 'é');
+        assert_eq!(bytes, b"!");
+    }
+
+    #[test]
+    fn test_text_errors() {
+        let error = Binding::parse_slice(&mut &b"letter = 1"[..]).unwrap_err();
+        let error = error.downcast::<ParseError>().unwrap();
+        assert_eq!(error.path(), "Binding.keyword");
+        assert_eq!(
+            error.to_string(),
+            "Binding.keyword at offset 3: keyword \"let\" continues as an identifier"
+        );
+
+        let error = Binding::parse_slice(&mut &b"let 1x = 1"[..]).unwrap_err();
+        let error = error.downcast::<ParseError>().unwrap();
+        assert_eq!(error.path(), "Binding.name");
+
+        let error = char::parse_slice(&mut &b"\xe2\x28\xa1"[..]).unwrap_err();
+        let error = error.downcast::<ParseError>().unwrap();
+        assert_eq!(error.offset(), 0);
+        assert!(error.cause().downcast_ref::<InvalidUtf8>().is_some());
+    }
+}
@@ -0,0 +1,185 @@
+//! Validators built from other validators.
+//!
+//! ```ignore
+//! define_tag!(Sign, b"+-");
+//!
+//! type HexDigit = Validated<u8, Or<IsAsciiDigit, InRange<0x61, 0x66>>>;
+//! type NotSign = Validated<u8, Not<OneOf<Sign>>>;
+//! ```
+//!
+//! The description of a combinator is built from those of its operands, like
+//! `IsAsciiDigit or in 97..=102`.
+
+use crate::{Escaped, TagBytes, Validator};
+use std::marker::PhantomData;
+use std::str;
+
+/// Values accepted by both `A` and `B`.
+#[derive(Debug, PartialEq, Eq)]
+pub struct And<A, B>(PhantomData<(A, B)>);
+
+/// Values accepted by `A` or `B`.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Or<A, B>(PhantomData<(A, B)>);
+
+/// Values not accepted by `A`.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Not<A>(PhantomData<A>);
+
+/// Numbers from `LO` to `HI` inclusive, or chars with these code points.
+#[derive(Debug, PartialEq, Eq)]
+pub struct InRange<const LO: i128, const HI: i128>;
+
+/// Bytes in `K::BYTES`, or chars in them read as UTF-8.
+#[derive(Debug, PartialEq, Eq)]
+pub struct OneOf<K: TagBytes>(PhantomData<K>);
+
+/// `description` as the operand of `And`, `Or` or `Not`, in parentheses if it contains `pattern`.
+fn operand(description: String, pattern: &[&str]) -> String {
+    if pattern.iter().any(|p| description.contains(p)) {
+        format!("({})", description)
+    } else {
+        description
+    }
+}
+
+impl<T, A: Validator<T>, B: Validator<T>> Validator<T> for And<A, B> {
+    fn validate(value: &T) -> bool {
+        A::validate(value) && B::validate(value)
+    }
+
+    fn description() -> String {
+        let compound = [" and ", " or "];
+        let (a, b) = (A::description(), B::description());
+        format!("{} and {}", operand(a, &compound), operand(b, &compound))
+    }
+}
+
+impl<T, A: Validator<T>, B: Validator<T>> Validator<T> for Or<A, B> {
+    fn validate(value: &T) -> bool {
+        A::validate(value) || B::validate(value)
+    }
+
+    fn description() -> String {
+        let compound = [" and ", " or "];
+        let (a, b) = (A::description(), B::description());
+        format!("{} or {}", operand(a, &compound), operand(b, &compound))
+    }
+}
+
+impl<T, A: Validator<T>> Validator<T> for Not<A> {
+    fn validate(value: &T) -> bool {
+        !A::validate(value)
+    }
+
+    fn description() -> String {
+        format!("not {}", operand(A::description(), &[" "]))
+    }
+}
+
+macro_rules! in_range {
+    ($($t:ty),*) => {
+        $(
+            impl<const LO: i128, const HI: i128> Validator<$t> for InRange<LO, HI> {
+                fn validate(value: &$t) -> bool {
+                    (LO..=HI).contains(&i128::from(*value))
+                }
+
+                fn description() -> String {
+                    format!("in {}..={}", LO, HI)
+                }
+            }
+        )*
+    };
+}
+
+in_range!(u8, u16, u32, u64, i8, i16, i32, i64);
+
+impl<const LO: i128, const HI: i128> Validator<char> for InRange<LO, HI> {
+    fn validate(value: &char) -> bool {
+        (LO..=HI).contains(&i128::from(u32::from(*value)))
+    }
+
+    fn description() -> String {
+        format!("in U+{:04X}..=U+{:04X}", LO, HI)
+    }
+}
+
+impl<K: TagBytes> Validator<u8> for OneOf<K> {
+    fn validate(value: &u8) -> bool {
+        K::BYTES.contains(value)
+    }
+
+    fn description() -> String {
+        format!("one of b\"{}\"", Escaped(K::BYTES))
+    }
+}
+
+impl<K: TagBytes> Validator<char> for OneOf<K> {
+    fn validate(value: &char) -> bool {
+        str::from_utf8(K::BYTES).is_ok_and(|chars| chars.contains(*value))
+    }
+
+    fn description() -> String {
+        format!("one of \"{}\"", String::from_utf8_lossy(K::BYTES))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    define_tag!(Sign, b"+-");
+    define_validator!(IsEven, |value: &u8| value % 2 == 0);
+
+    type HexDigit = Validated<u8, Or<IsAsciiDigit, InRange<0x61, 0x66>>>;
+    type Symbol = Validated<u8, And<Not<OneOf<Sign>>, Not<IsAsciiDigit>>>;
+
+    #[derive(Destruct, Debug, PartialEq, Eq)]
+    #[destruct(parsable, emit)]
+    struct Token {
+        sign: Validated<u8, OneOf<Sign>>,
+        digits: Many1<HexDigit>,
+        symbol: Symbol,
+    }
+
+    #[test]
+    fn test_combinators() {
+        let mut bytes = &b"-9af!"[..];
+        let token = Token::parse_slice(&mut bytes).unwrap();
+        assert_eq!(token.digits.0.len(), 3);
+        assert_eq!(to_bytes(&token).unwrap(), b"-9af!");
+
+        let letter = Validated::<char, And<IsAlphabetic, InRange<0x61, 0x7a>>>::parse_slice(
+            &mut "é".as_bytes(),
+        );
+        assert!(letter.is_err());
+        assert!(Validated::<u8, IsEven>::parse_slice(&mut &[4][..]).is_ok());
+        assert!(Validated::<char, OneOf<Sign>>::parse_slice(&mut "+".as_bytes()).is_ok());
+    }
+
+    #[test]
+    fn test_descriptions() {
+        assert_eq!(IsEven::description(), "IsEven");
+        assert_eq!(
+            <Or<IsAsciiDigit, InRange<0x61, 0x66>> as Validator<u8>>::description(),
+            "IsAsciiDigit or in 97..=102"
+        );
+        assert_eq!(
+            <Not<Or<IsEven, OneOf<Sign>>> as Validator<u8>>::description(),
+            "not (IsEven or one of b\"+-\")"
+        );
+        assert_eq!(
+            <InRange<0x61, 0x7a> as Validator<char>>::description(),
+            "in U+0061..=U+007A"
+        );
+
+        let error = Token::parse_slice(&mut &b"+1+"[..]).unwrap_err();
+        let error = error.downcast::<ParseError>().unwrap();
+        assert_eq!(error.path(), "Token.symbol");
+        assert_eq!(
+            error.to_string(),
+            "Token.symbol at offset 2: Can not validate not (one of b\"+-\") and not IsAsciiDigit"
+        );
+    }
+}